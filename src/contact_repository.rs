@@ -1,23 +1,78 @@
 use crate::models::contact::Contact;
+use crate::sqlite::Sqlite;
 use msnp11_sdk::MsnpList;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, mpsc};
+use tokio::runtime::Handle;
 
 #[derive(Clone, Default)]
 pub struct ContactRepository {
     contacts: Arc<RwLock<HashMap<Arc<String>, Contact>>>,
+    aliases: Arc<RwLock<HashMap<Arc<String>, Arc<String>>>>,
+    cache: Option<(Sqlite, Arc<String>)>,
+    // Changes to write to the cache, None removing the contact
+    cache_writes: Option<mpsc::Sender<(Arc<String>, Option<Contact>)>>,
 }
 
 impl ContactRepository {
-    pub fn new() -> Self {
+    // Starts with the account's cached contact list and keeps the cache up to date with every change
+    pub fn with_cache(sqlite: Sqlite, user_email: Arc<String>, handle: Handle) -> Self {
+        let contacts = sqlite
+            .select_contacts(&user_email)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|contact| (contact.email.clone(), contact))
+            .collect();
+
+        let aliases = sqlite
+            .select_contact_aliases(&user_email)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(email, alias)| (Arc::new(email), Arc::new(alias)))
+            .collect();
+
+        // Writes are kept off the UI thread, and the ones that pile up while a batch is being
+        // written go in the next transaction together
+        let (cache_writes, receiver) = mpsc::channel::<(Arc<String>, Option<Contact>)>();
+        let writer_sqlite = sqlite.clone();
+        let writer_email = user_email.clone();
+        handle.spawn_blocking(move || {
+            while let Ok(write) = receiver.recv() {
+                let writes: HashMap<_, _> =
+                    std::iter::once(write).chain(receiver.try_iter()).collect();
+                let writes: Vec<_> = writes.into_iter().collect();
+                let _ = writer_sqlite.write_contacts(&writer_email, &writes);
+            }
+        });
+
         Self {
-            contacts: Arc::new(RwLock::new(HashMap::new())),
+            contacts: Arc::new(RwLock::new(contacts)),
+            aliases: Arc::new(RwLock::new(aliases)),
+            cache: Some((sqlite, user_email)),
+            cache_writes: Some(cache_writes),
         }
     }
 
     pub fn get_contact(&self, email: &String) -> Option<Contact> {
         if let Ok(contacts) = self.contacts.read() {
-            contacts.get(email).cloned()
+            contacts
+                .get(email)
+                .cloned()
+                .map(|contact| self.with_alias(contact))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_contacts(&self) -> Option<Vec<Contact>> {
+        if let Ok(contacts) = self.contacts.read() {
+            Some(
+                contacts
+                    .values()
+                    .cloned()
+                    .map(|contact| self.with_alias(contact))
+                    .collect(),
+            )
         } else {
             None
         }
@@ -30,6 +85,7 @@ impl ContactRepository {
                     .values()
                     .filter(|contact| contact.lists.contains(&list))
                     .cloned()
+                    .map(|contact| self.with_alias(contact))
                     .collect(),
             )
         } else {
@@ -37,6 +93,33 @@ impl ContactRepository {
         }
     }
 
+    pub fn get_alias(&self, email: &String) -> Option<Arc<String>> {
+        if let Ok(aliases) = self.aliases.read() {
+            aliases.get(email).cloned()
+        } else {
+            None
+        }
+    }
+
+    // Aliases are only stored locally, removing it if None is passed
+    pub fn set_alias(&self, email: Arc<String>, alias: Option<Arc<String>>) {
+        if let Some((sqlite, user_email)) = &self.cache {
+            let _ = sqlite.set_contact_alias(
+                user_email,
+                &email,
+                alias.as_ref().map(|alias| alias.as_str()),
+            );
+        }
+
+        if let Ok(mut aliases) = self.aliases.write() {
+            if let Some(alias) = alias {
+                aliases.insert(email, alias);
+            } else {
+                aliases.remove(&email);
+            }
+        }
+    }
+
     pub fn add_contacts(&self, contacts: &[Contact]) {
         if let Ok(mut contacts_lock) = self.contacts.write() {
             contacts_lock.reserve(contacts.len());
@@ -44,6 +127,8 @@ impl ContactRepository {
                 contacts_lock.insert(contact.email.clone(), contact.clone());
             }
         }
+
+        self.cache_contacts(contacts);
     }
 
     pub fn update_contacts(&self, contacts: &[Contact]) {
@@ -52,5 +137,30 @@ impl ContactRepository {
                 contacts_lock.insert(contact.email.clone(), contact.clone());
             }
         }
+
+        self.cache_contacts(contacts);
+    }
+
+    pub fn remove_contact(&self, email: &Arc<String>) {
+        if let Ok(mut contacts) = self.contacts.write() {
+            contacts.remove(email);
+        }
+
+        if let Some(cache_writes) = &self.cache_writes {
+            let _ = cache_writes.send((email.clone(), None));
+        }
+    }
+
+    fn with_alias(&self, mut contact: Contact) -> Contact {
+        contact.alias = self.get_alias(&contact.email);
+        contact
+    }
+
+    fn cache_contacts(&self, contacts: &[Contact]) {
+        if let Some(cache_writes) = &self.cache_writes {
+            for contact in contacts {
+                let _ = cache_writes.send((contact.email.clone(), Some(contact.clone())));
+            }
+        }
     }
 }
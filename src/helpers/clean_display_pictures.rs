@@ -0,0 +1,27 @@
+use crate::sqlite::Sqlite;
+
+// Contacts' pictures are kept this long after they were last shown, in case they come back
+const UNUSED_FOR: i64 = 30 * 24 * 60 * 60;
+
+pub fn unused_since() -> i64 {
+    chrono::Local::now().timestamp() - UNUSED_FOR
+}
+
+// Deletes unused display pictures and compacts the database, returning how many pictures were
+// deleted and how many bytes the database shrank by
+pub fn clean_display_pictures(sqlite: &Sqlite) -> anyhow::Result<(usize, u64)> {
+    let size_before = sqlite.database_size()?;
+    let deleted = sqlite.delete_unused_display_pictures(unused_since())?;
+    sqlite.vacuum()?;
+
+    let size_after = sqlite.database_size()?;
+    Ok((deleted, size_before.saturating_sub(size_after)))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024. * 1024.))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.)
+    }
+}
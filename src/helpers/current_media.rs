@@ -0,0 +1,27 @@
+// Current media is sent as "App\0Type\0Enabled\0Format\0Arg0\0Arg1...", where the separators are a
+// literal backslash and zero, and the format has placeholders like {0} for each argument
+pub fn format_current_media(current_media: &str) -> Option<String> {
+    let mut fields = current_media.split("\\0");
+    let _app = fields.next()?;
+    let media_type = fields.next()?;
+    if fields.next()? != "1" {
+        return None;
+    }
+
+    let mut text = fields.next()?.to_string();
+    for (index, argument) in fields.enumerate() {
+        text = text.replace(&format!("{{{index}}}"), argument);
+    }
+
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(match media_type {
+        "Music" => format!("Listening to: {text}"),
+        "Games" => format!("Playing: {text}"),
+        "Office" => format!("Working on: {text}"),
+        _ => text.to_string(),
+    })
+}
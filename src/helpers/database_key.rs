@@ -0,0 +1,26 @@
+use keyring::Entry;
+use r2d2_sqlite::rusqlite;
+
+// Not an e-mail address, so it can't clash with saved passwords
+const KEYRING_USER: &str = "database-key";
+
+pub fn get_database_key() -> Option<String> {
+    Entry::new("meowsn", KEYRING_USER).ok()?.get_password().ok()
+}
+
+pub fn create_database_key() -> anyhow::Result<String> {
+    let key: String = rusqlite::Connection::open_in_memory()?.query_row(
+        "SELECT hex(randomblob(32))",
+        [],
+        |row| row.get(0),
+    )?;
+
+    Entry::new("meowsn", KEYRING_USER)?.set_password(&key)?;
+    Ok(key)
+}
+
+pub fn delete_database_key() {
+    if let Ok(entry) = Entry::new("meowsn", KEYRING_USER) {
+        let _ = entry.delete_credential();
+    }
+}
@@ -0,0 +1,424 @@
+use crate::contact_repository::ContactRepository;
+use crate::helpers::msn_color::color_from_msn;
+use crate::models::message::Message;
+use crate::sqlite::Sqlite;
+use chrono::{DateTime, Local, TimeZone};
+use rfd::{AsyncFileDialog, FileHandle};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::Arc;
+
+struct Conversation {
+    is_group: bool,
+    participants: BTreeSet<Arc<String>>,
+    messages: Vec<Message>,
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    account: &'a str,
+    exported_at: String,
+    conversations: Vec<JsonConversation<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonConversation<'a> {
+    is_group: bool,
+    participants: Vec<JsonParticipant<'a>>,
+    messages: Vec<JsonMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonParticipant<'a> {
+    email: &'a str,
+    display_name: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+    id: Option<i64>,
+    sender: &'a str,
+    sender_display_name: &'a str,
+    receiver: Option<&'a str>,
+    timestamp: i64,
+    time: String,
+    text: &'a str,
+    is_nudge: bool,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    color: Option<String>,
+    errored: bool,
+}
+
+pub fn pick_export_file(file_name: &str) -> impl Future<Output = Option<FileHandle>> + use<> {
+    AsyncFileDialog::new()
+        .add_filter("Web page", &["html"])
+        .add_filter("Plain text", &["txt"])
+        .add_filter("JSON", &["json"])
+        .set_file_name(file_name)
+        .set_title("Export history")
+        .save_file()
+}
+
+// Exports every stored message, or only the ones exchanged with a contact, in the format
+// matching the chosen file's extension. Returns the number of exported messages, or None if
+// the dialog was cancelled.
+pub async fn export_history(
+    file_future: impl Future<Output = Option<FileHandle>>,
+    sqlite: Sqlite,
+    contact_repository: ContactRepository,
+    user_email: Arc<String>,
+    user_display_name: Arc<String>,
+    contact: Option<Arc<String>>,
+) -> anyhow::Result<Option<usize>> {
+    let Some(file) = file_future.await else {
+        return Ok(None);
+    };
+
+    let messages = sqlite
+        .select_messages_for_export(&user_email, contact.as_ref().map(|email| email.as_str()))?;
+    let message_count = messages.len();
+    let conversations = group_conversations(&user_email, messages);
+
+    let display_name = |email: &Arc<String>| {
+        if *email == user_email {
+            user_display_name.clone()
+        } else if let Some(contact) = contact_repository.get_contact(email) {
+            contact.display_name
+        } else {
+            email.clone()
+        }
+    };
+
+    let extension = file
+        .path()
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    let contents = match extension.as_deref() {
+        Some("html") | Some("htm") => to_html(&user_email, &conversations, display_name),
+        Some("json") => to_json(&user_email, &conversations, display_name)?,
+        _ => to_text(&user_email, &conversations, display_name),
+    };
+
+    std::fs::write(file.path(), contents)?;
+    Ok(Some(message_count))
+}
+
+fn group_conversations(user_email: &Arc<String>, messages: Vec<Message>) -> Vec<Conversation> {
+    let mut conversations: Vec<Conversation> = Vec::new();
+    let mut indices = HashMap::new();
+
+    for message in messages {
+        // One-on-one messages are grouped by contact, group messages by their participants
+        let key = if let Some(participants) = &message.participants {
+            format!("group:{participants}")
+        } else if message.sender == *user_email {
+            match &message.receiver {
+                Some(receiver) => format!("contact:{receiver}"),
+                None => "group".to_string(),
+            }
+        } else if message.receiver.is_some() {
+            format!("contact:{}", message.sender)
+        } else {
+            "group".to_string()
+        };
+
+        let index = *indices.entry(key).or_insert_with(|| {
+            conversations.push(Conversation {
+                is_group: message.participants.is_some(),
+                participants: BTreeSet::new(),
+                messages: Vec::new(),
+            });
+
+            conversations.len() - 1
+        });
+
+        let conversation = &mut conversations[index];
+        if let Some(participants) = &message.participants {
+            for participant in participants.split(',').filter(|email| !email.is_empty()) {
+                conversation
+                    .participants
+                    .insert(Arc::new(participant.to_string()));
+            }
+        }
+
+        for participant in [Some(&message.sender), message.receiver.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if participant != user_email {
+                conversation.participants.insert(participant.clone());
+            }
+        }
+
+        conversation.messages.push(message);
+    }
+
+    conversations
+}
+
+// Messages from before timestamps were recorded are stored with 0
+fn local_time(timestamp: i64) -> Option<DateTime<Local>> {
+    if timestamp == 0 {
+        return None;
+    }
+
+    Local.timestamp_opt(timestamp, 0).single()
+}
+
+fn conversation_title(
+    conversation: &Conversation,
+    display_name: impl Fn(&Arc<String>) -> Arc<String>,
+) -> String {
+    let participants = conversation
+        .participants
+        .iter()
+        .map(|email| format!("{} <{email}>", display_name(email)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if conversation.is_group || conversation.participants.len() != 1 {
+        format!("Group conversation with {participants}")
+    } else {
+        format!("Conversation with {participants}")
+    }
+}
+
+fn to_text(
+    user_email: &str,
+    conversations: &[Conversation],
+    display_name: impl Fn(&Arc<String>) -> Arc<String>,
+) -> String {
+    let mut text = format!("Message history for {user_email}\n");
+    for conversation in conversations {
+        let title = conversation_title(conversation, &display_name);
+        let _ = write!(text, "\n{title}\n{}\n", "=".repeat(title.chars().count()));
+
+        let mut previous_date = None;
+        for message in &conversation.messages {
+            let date_time = local_time(message.timestamp);
+            if let Some(date_time) = date_time
+                && previous_date != Some(date_time.date_naive())
+            {
+                previous_date = Some(date_time.date_naive());
+                let _ = writeln!(text, "\n{}", date_time.format("%A, %B %-d, %Y"));
+            }
+
+            let time = date_time
+                .map(|date_time| date_time.format("%H:%M:%S").to_string())
+                .unwrap_or_else(|| "unknown time".to_string());
+
+            if message.is_nudge {
+                let _ = writeln!(text, "[{time}] *** {}", message.text);
+                continue;
+            }
+
+            // Styles are kept as Messenger Plus! tags
+            let mut message_text = message.text.clone();
+            for (enabled, tag) in [
+                (message.bold, "b"),
+                (message.italic, "i"),
+                (message.underline, "u"),
+                (message.strikethrough, "s"),
+            ] {
+                if enabled {
+                    message_text = format!("[{tag}]{message_text}[/{tag}]");
+                }
+            }
+
+            let _ = writeln!(
+                text,
+                "[{time}] {}{}: {message_text}",
+                if message.errored {
+                    "(not delivered) "
+                } else {
+                    ""
+                },
+                display_name(&message.sender)
+            );
+        }
+    }
+
+    text
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn css_color(color: &str) -> Option<String> {
+    color_from_msn(color)
+        .map(|color| format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b()))
+}
+
+fn to_html(
+    user_email: &str,
+    conversations: &[Conversation],
+    display_name: impl Fn(&Arc<String>) -> Arc<String>,
+) -> String {
+    let user_email = escape_html(user_email);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Message history for {user_email}</title>\n<style>\n\
+        body {{ font-family: \"Segoe UI\", Tahoma, sans-serif; font-size: 14px; margin: 20px; }}\n\
+        h2 {{ font-size: 16px; border-bottom: 1px solid #ccc; padding-bottom: 4px; margin-top: 30px; }}\n\
+        .date {{ text-align: center; color: #777; margin: 12px 0; }}\n\
+        .message {{ margin: 6px 0; }}\n\
+        .time {{ color: #777; font-size: 12px; }}\n\
+        .text {{ margin-left: 16px; white-space: pre-wrap; }}\n\
+        .nudge, .errored {{ border-top: 1px solid #ccc; border-bottom: 1px solid #ccc; padding: 4px 0; }}\n\
+        .errored .text {{ color: #888; }}\n\
+        </style>\n</head>\n<body>\n<h1>Message history for {user_email}</h1>\n"
+    );
+
+    for conversation in conversations {
+        let _ = writeln!(
+            html,
+            "<h2>{}</h2>",
+            escape_html(&conversation_title(conversation, &display_name))
+        );
+
+        let mut previous_date = None;
+        for message in &conversation.messages {
+            let date_time = local_time(message.timestamp);
+            if let Some(date_time) = date_time
+                && previous_date != Some(date_time.date_naive())
+            {
+                previous_date = Some(date_time.date_naive());
+                let _ = writeln!(
+                    html,
+                    "<div class=\"date\">{}</div>",
+                    date_time.format("%A, %B %-d, %Y")
+                );
+            }
+
+            let time = date_time
+                .map(|date_time| date_time.format("%H:%M").to_string())
+                .unwrap_or_else(|| "unknown time".to_string());
+
+            if message.is_nudge {
+                let _ = writeln!(
+                    html,
+                    "<div class=\"message nudge\">{} <span class=\"time\">({time})</span></div>",
+                    escape_html(&message.text)
+                );
+
+                continue;
+            }
+
+            let mut style = String::new();
+            if message.bold {
+                style.push_str("font-weight: bold; ");
+            }
+
+            if message.italic {
+                style.push_str("font-style: italic; ");
+            }
+
+            match (message.underline, message.strikethrough) {
+                (true, true) => style.push_str("text-decoration: underline line-through; "),
+                (true, false) => style.push_str("text-decoration: underline; "),
+                (false, true) => style.push_str("text-decoration: line-through; "),
+                (false, false) => (),
+            }
+
+            if let Some(color) = css_color(&message.color) {
+                let _ = write!(style, "color: {color};");
+            }
+
+            let header = if message.errored {
+                "The following message could not be delivered to all recipients:".to_string()
+            } else {
+                format!("{} said:", escape_html(&display_name(&message.sender)))
+            };
+
+            let _ = writeln!(
+                html,
+                "<div class=\"message{}\"><div>{header} <span class=\"time\">{time}</span></div>\
+                <div class=\"text\" style=\"{}\">{}</div></div>",
+                if message.errored { " errored" } else { "" },
+                style.trim_end(),
+                escape_html(&message.text)
+            );
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn to_json(
+    user_email: &str,
+    conversations: &[Conversation],
+    display_name: impl Fn(&Arc<String>) -> Arc<String>,
+) -> anyhow::Result<String> {
+    let display_names: HashMap<&Arc<String>, Arc<String>> = conversations
+        .iter()
+        .flat_map(|conversation| {
+            conversation
+                .participants
+                .iter()
+                .chain(conversation.messages.iter().map(|message| &message.sender))
+        })
+        .map(|email| (email, display_name(email)))
+        .collect();
+
+    let export = JsonExport {
+        account: user_email,
+        exported_at: Local::now().to_rfc3339(),
+        conversations: conversations
+            .iter()
+            .map(|conversation| JsonConversation {
+                is_group: conversation.is_group,
+                participants: conversation
+                    .participants
+                    .iter()
+                    .map(|email| JsonParticipant {
+                        email,
+                        display_name: &display_names[email],
+                    })
+                    .collect(),
+                messages: conversation
+                    .messages
+                    .iter()
+                    .map(|message| JsonMessage {
+                        id: message.id,
+                        sender: &message.sender,
+                        sender_display_name: &display_names[&message.sender],
+                        receiver: message.receiver.as_ref().map(|receiver| receiver.as_str()),
+                        timestamp: message.timestamp,
+                        time: local_time(message.timestamp)
+                            .map(|date_time| date_time.to_rfc3339())
+                            .unwrap_or_default(),
+                        text: &message.text,
+                        is_nudge: message.is_nudge,
+                        bold: message.bold,
+                        italic: message.italic,
+                        underline: message.underline,
+                        strikethrough: message.strikethrough,
+                        color: css_color(&message.color),
+                        errored: message.errored,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_string_pretty(&export)?)
+}
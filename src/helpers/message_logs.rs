@@ -0,0 +1,307 @@
+use crate::helpers::msn_color::color_to_msn;
+use crate::models::contact::Contact;
+use crate::models::message::Message;
+use crate::models::message_log::{FriendlyName, LogEntry, MessageLog};
+use crate::sqlite::Sqlite;
+use anyhow::Context;
+use chrono::{DateTime, Local, NaiveDateTime};
+use eframe::egui;
+use rfd::FileHandle;
+use std::path::Path;
+use std::sync::Arc;
+
+// Reads the MessageLog XML files written by MSN Messenger and Windows Live Messenger. Returns
+// the parsed logs and an error line for every file that couldn't be read, or None if the
+// dialog was cancelled.
+pub async fn read_message_logs(
+    files_future: impl Future<Output = Option<Vec<FileHandle>>>,
+    contacts: Vec<Contact>,
+    user_display_name: Arc<String>,
+) -> Option<(Vec<MessageLog>, Vec<String>)> {
+    let files = files_future.await?;
+    let mut logs = Vec::new();
+    let mut errors = Vec::new();
+
+    for file in files {
+        match read_message_log(file.path(), &contacts, &user_display_name) {
+            Ok(log) => logs.push(log),
+            Err(error) => errors.push(format!("{}: {error:#}", file.file_name())),
+        }
+    }
+
+    Some((logs, errors))
+}
+
+fn read_message_log(
+    path: &Path,
+    contacts: &[Contact],
+    user_display_name: &str,
+) -> anyhow::Result<MessageLog> {
+    let bytes = std::fs::read(path)?;
+
+    // Older clients wrote their logs as UTF-16
+    let xml = if let Some(bytes) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(&bytes))
+            .to_string()
+    };
+
+    let document = roxmltree::Document::parse(&xml).context("Not a valid XML file")?;
+    let root = document.root_element();
+    if !root.has_tag_name("Log") {
+        anyhow::bail!("Not a Messenger message log");
+    }
+
+    let mut entries = Vec::new();
+    for message in root.children().filter(|node| node.has_tag_name("Message")) {
+        let users = |tag_name: &str| -> Vec<String> {
+            message
+                .children()
+                .filter(|node| node.has_tag_name(tag_name))
+                .flat_map(|node| node.children().filter(|node| node.has_tag_name("User")))
+                .filter_map(|user| user.attribute("FriendlyName"))
+                .map(|name| name.to_string())
+                .collect()
+        };
+
+        let Some(from) = users("From").into_iter().next() else {
+            continue;
+        };
+
+        let Some(timestamp) = message_timestamp(message) else {
+            continue;
+        };
+
+        let text_node = message.children().find(|node| node.has_tag_name("Text"));
+        let text = text_node
+            .map(|node| {
+                node.descendants()
+                    .filter(|node| node.is_text())
+                    .filter_map(|node| node.text())
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+
+        let mut entry = LogEntry {
+            from,
+            to: users("To"),
+            session_id: message.attribute("SessionID").unwrap_or("0").to_string(),
+            timestamp,
+            text,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            color: "0".to_string(),
+        };
+
+        let style = text_node
+            .and_then(|node| node.attribute("Style"))
+            .unwrap_or_default();
+
+        for declaration in style.split(';') {
+            if let Some((property, value)) = declaration.split_once(':') {
+                let value = value.trim().to_lowercase();
+                match property.trim().to_lowercase().as_str() {
+                    "font-weight" => entry.bold = value == "bold",
+                    "font-style" => entry.italic = value == "italic",
+                    "text-decoration" => {
+                        entry.underline = value.contains("underline");
+                        entry.strikethrough = value.contains("line-through");
+                    }
+
+                    "color" => {
+                        if let Ok(color) = egui::Color32::from_hex(&value) {
+                            entry.color = color_to_msn(color);
+                        }
+                    }
+
+                    _ => (),
+                }
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    if entries.is_empty() {
+        anyhow::bail!("No messages found");
+    }
+
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let contact = guess_contact(&file_stem, contacts);
+    let mut friendly_names: Vec<FriendlyName> = Vec::new();
+    for name in entries
+        .iter()
+        .flat_map(|entry| std::iter::once(&entry.from).chain(entry.to.iter()))
+    {
+        if !friendly_names
+            .iter()
+            .any(|friendly_name| friendly_name.name == *name)
+        {
+            friendly_names.push(FriendlyName {
+                name: name.clone(),
+                is_user: name == user_display_name,
+            });
+        }
+    }
+
+    // Logs only have friendly names, so if the user's isn't obvious, guess that they're whoever
+    // isn't the contact in a two person log
+    if !friendly_names
+        .iter()
+        .any(|friendly_name| friendly_name.is_user)
+        && friendly_names.len() == 2
+        && let Some(contact) = contact
+        && let Some(index) = friendly_names
+            .iter()
+            .position(|friendly_name| friendly_name.name == *contact.display_name)
+    {
+        friendly_names[1 - index].is_user = true;
+    }
+
+    Ok(MessageLog {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        contact_email: contact
+            .map(|contact| contact.email.to_string())
+            .unwrap_or_default(),
+        friendly_names,
+        entries,
+    })
+}
+
+fn message_timestamp(message: roxmltree::Node) -> Option<i64> {
+    if let Some(date_time) = message.attribute("DateTime")
+        && let Ok(date_time) = DateTime::parse_from_rfc3339(date_time)
+    {
+        return Some(date_time.timestamp());
+    }
+
+    let date_time = format!(
+        "{} {}",
+        message.attribute("Date")?,
+        message.attribute("Time")?
+    );
+
+    NaiveDateTime::parse_from_str(&date_time, "%m/%d/%Y %I:%M:%S %p")
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|date_time| date_time.timestamp())
+}
+
+// Log files are named after the contact's e-mail address with anything that isn't alphanumeric
+// removed, followed by a number
+fn guess_contact<'a>(file_stem: &str, contacts: &'a [Contact]) -> Option<&'a Contact> {
+    let alphanumeric = |text: &str| {
+        text.chars()
+            .filter(|character| character.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+
+    let file_stem = alphanumeric(file_stem);
+    contacts
+        .iter()
+        .filter_map(|contact| {
+            let email = alphanumeric(&contact.email);
+            let local_part = alphanumeric(contact.email.split('@').next().unwrap_or_default());
+
+            [email, local_part]
+                .into_iter()
+                .filter(|prefix| !prefix.is_empty() && file_stem.starts_with(prefix))
+                .map(|prefix| prefix.len())
+                .max()
+                .map(|length| (length, contact))
+        })
+        .max_by_key(|(length, _)| *length)
+        .map(|(_, contact)| contact)
+}
+
+// Returns a line describing what was imported from every log
+pub fn import_message_logs(
+    sqlite: &Sqlite,
+    user_email: &Arc<String>,
+    logs: &[MessageLog],
+) -> Vec<String> {
+    let mut report = Vec::new();
+    for log in logs {
+        let contact_email = Arc::new(log.contact_email.trim().to_lowercase());
+        let email = |name: &String| {
+            if log
+                .friendly_names
+                .iter()
+                .any(|friendly_name| friendly_name.name == *name && friendly_name.is_user)
+            {
+                user_email.clone()
+            } else {
+                contact_email.clone()
+            }
+        };
+
+        let messages: Vec<Message> = log
+            .entries
+            .iter()
+            .map(|entry| {
+                let sender = email(&entry.from);
+
+                // Messages sent to more than one person belong to a group conversation. Logs only
+                // name the other participants, so the contact the log belongs to stands in for them.
+                let (receiver, session_id, participants) = if entry.to.len() > 1 {
+                    let file_stem = log.file_name.trim_end_matches(".xml");
+                    (
+                        None,
+                        Some(Arc::new(format!("wlm-{file_stem}-{}", entry.session_id))),
+                        Some(contact_email.clone()),
+                    )
+                } else if sender == *user_email {
+                    (Some(contact_email.clone()), None, None)
+                } else {
+                    (Some(user_email.clone()), None, None)
+                };
+
+                Message {
+                    sender,
+                    receiver,
+                    text: entry.text.clone(),
+                    bold: entry.bold,
+                    italic: entry.italic,
+                    underline: entry.underline,
+                    strikethrough: entry.strikethrough,
+                    color: entry.color.clone(),
+                    session_id,
+                    participants,
+                    timestamp: entry.timestamp,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        report.push(
+            match sqlite.insert_imported_messages(user_email, &messages) {
+                Ok(inserted) => format!(
+                    "{}: imported {inserted} messages, skipped {} already in history",
+                    log.file_name,
+                    messages.len() - inserted
+                ),
+
+                Err(error) => format!("{}: could not import messages: {error}", log.file_name),
+            },
+        );
+    }
+
+    report
+}
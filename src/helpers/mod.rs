@@ -1,5 +1,13 @@
+pub mod clean_display_pictures;
+pub mod current_media;
+pub mod database_key;
+pub mod export_history;
 pub mod get_config;
+pub mod message_logs;
+pub mod msn_color;
 pub mod notify_new_version;
+pub mod outbox;
 pub mod pick_display_picture;
+pub mod prune_history;
 pub mod run_future;
 pub mod sign_in_async;
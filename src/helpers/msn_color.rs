@@ -0,0 +1,22 @@
+use eframe::egui::Color32;
+
+// MSN colors are BGR hex without leading zeros. Black, "0", is what clients send by default, so
+// it's treated as no color at all
+pub fn color_from_msn(color: &str) -> Option<Color32> {
+    let bgr = u32::from_str_radix(color.trim(), 16)
+        .ok()
+        .filter(|bgr| *bgr > 0 && *bgr <= 0xffffff)?;
+
+    Some(Color32::from_rgb(
+        bgr as u8,
+        (bgr >> 8) as u8,
+        (bgr >> 16) as u8,
+    ))
+}
+
+pub fn color_to_msn(color: Color32) -> String {
+    format!(
+        "{:x}",
+        u32::from(color.b()) << 16 | u32::from(color.g()) << 8 | u32::from(color.r())
+    )
+}
@@ -0,0 +1,71 @@
+use crate::models::message::Message;
+use crate::models::outbox_entry::OutboxEntry;
+use crate::sqlite::Sqlite;
+use msnp11_sdk::{MessagingError, Switchboard};
+
+pub const MAX_ATTEMPTS: u32 = 10;
+pub const IDLE_CHECK: i64 = 60;
+
+// Waits 15 seconds after the first failure, doubling every time up to 10 minutes
+pub fn retry_delay(attempts: u32) -> i64 {
+    (15 * 2_i64.pow(attempts.saturating_sub(1).min(6))).min(10 * 60)
+}
+
+// Picks the entries that are due, or all of them that aren't already being sent if forced,
+// returning them along with when the next one left out is due
+pub fn due_entries(entries: Vec<OutboxEntry>, force: bool, now: i64) -> (Vec<OutboxEntry>, i64) {
+    let mut due_entries = Vec::new();
+    let mut next_attempt = i64::MAX;
+
+    for entry in entries {
+        if entry.attempts >= MAX_ATTEMPTS {
+            continue;
+        }
+
+        // An attempt whose result never came back is retried once it's due again
+        if entry.next_attempt <= now || force && !entry.sending {
+            due_entries.push(entry);
+        } else {
+            next_attempt = next_attempt.min(entry.next_attempt);
+        }
+    }
+
+    (due_entries, next_attempt)
+}
+
+// Counts the attempt before sending, so a message isn't sent twice while it's in flight. Returns
+// when the next attempt is due, if there's one left.
+pub fn start_attempt(sqlite: &Sqlite, entry: &OutboxEntry, now: i64) -> Option<i64> {
+    let id = entry.message.id?;
+    let attempts = entry.attempts + 1;
+    let next_attempt = now + retry_delay(attempts);
+    let _ = sqlite.start_outbox_attempt(id, attempts, next_attempt);
+
+    (attempts < MAX_ATTEMPTS).then_some(next_attempt)
+}
+
+// Delivered messages leave the outbox, failed ones wait for their next attempt
+pub fn finish_attempt(sqlite: &Sqlite, message_id: i64, delivered: bool) {
+    if delivered {
+        let _ = sqlite.mark_message_delivered(message_id);
+    } else {
+        let _ = sqlite.fail_outbox_attempt(message_id);
+    }
+}
+
+pub async fn send(switchboard: &Switchboard, message: &Message) -> Result<(), MessagingError> {
+    if message.is_nudge {
+        switchboard.send_nudge().await
+    } else {
+        switchboard
+            .send_text_message(&msnp11_sdk::PlainText {
+                bold: message.bold,
+                italic: message.italic,
+                underline: message.underline,
+                strikethrough: message.strikethrough,
+                color: message.color.clone(),
+                text: message.text.clone(),
+            })
+            .await
+    }
+}
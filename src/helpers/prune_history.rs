@@ -0,0 +1,68 @@
+use crate::helpers::clean_display_pictures::unused_since;
+use crate::models::transcript::TranscriptKind;
+use crate::settings;
+use crate::settings::HistoryRetention;
+use crate::sqlite::Sqlite;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+const PRUNE_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(6 * 60 * 60);
+const PRESENCE_HISTORY_DAYS: i64 = 90;
+
+// Prunes the account's history according to the retention settings, along with display pictures
+// nothing uses anymore and old presence changes, when signing in and every few hours afterwards,
+// until cancelled
+pub async fn prune_history_periodically(
+    sqlite: Sqlite,
+    user_email: Arc<String>,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        let _ = prune_history(&sqlite, &user_email);
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            _ = tokio::time::sleep(PRUNE_INTERVAL) => (),
+        }
+    }
+}
+
+fn prune_history(sqlite: &Sqlite, user_email: &str) -> anyhow::Result<usize> {
+    let settings = settings::get_settings().unwrap_or_default();
+    let now = chrono::Local::now().timestamp();
+    let mut deleted = 0;
+
+    for transcript in sqlite.select_transcripts(user_email)? {
+        let retention = match &transcript.kind {
+            TranscriptKind::Contact(contact) => settings
+                .history_retention_overrides
+                .get(contact.as_str())
+                .copied()
+                .unwrap_or(settings.history_retention),
+
+            TranscriptKind::Group(_) => settings.history_retention,
+        };
+
+        deleted += match retention {
+            HistoryRetention::Everything => 0,
+            HistoryRetention::Days(days) => sqlite.delete_transcript_messages_before(
+                user_email,
+                &transcript.kind,
+                now - i64::from(days) * 24 * 60 * 60,
+            )?,
+
+            HistoryRetention::Messages(messages) => {
+                sqlite.delete_transcript_messages_beyond(user_email, &transcript.kind, messages)?
+            }
+        };
+    }
+
+    let deleted_pictures = sqlite.delete_unused_display_pictures(unused_since())?;
+    let deleted_presence = sqlite
+        .delete_presence_history_before(user_email, now - PRESENCE_HISTORY_DAYS * 24 * 60 * 60)?;
+
+    if deleted > 0 || deleted_pictures > 0 || deleted_presence > 0 {
+        sqlite.vacuum()?;
+    }
+
+    Ok(deleted)
+}
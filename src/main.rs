@@ -6,6 +6,7 @@
 mod contact_repository;
 mod helpers;
 mod main_window;
+mod migrations;
 mod models;
 mod screens;
 mod settings;
@@ -4,8 +4,11 @@ use crate::models::display_picture::DisplayPicture;
 use crate::models::sign_in_return::SignInReturn;
 use crate::screens::contacts::contacts;
 use crate::screens::conversation::conversation;
+use crate::screens::offline_contacts;
 use crate::screens::personal_settings;
 use crate::screens::sign_in::sign_in;
+use crate::screens::unlock_database;
+use crate::screens::unlock_database::OpenedDatabase;
 use crate::sqlite::Sqlite;
 use crate::visuals;
 use eframe::egui;
@@ -15,12 +18,18 @@ use std::sync::{Arc, mpsc};
 use tokio::runtime::Handle;
 
 enum Screen {
+    // The database couldn't be opened, so the app quits once the error has been read
+    DatabaseError,
+    UnlockDatabase(unlock_database::UnlockDatabase),
     SignIn(sign_in::SignIn),
     Contacts(Box<contacts::Contacts>),
+    OfflineContacts(Box<offline_contacts::OfflineContacts>),
 }
 
 pub enum Message {
+    DatabaseUnlocked(Sqlite),
     SignIn(SignInReturn),
+    BrowseOffline(Arc<String>),
     SignOut,
     OpenPersonalSettings(
         Option<String>,
@@ -50,10 +59,12 @@ pub enum Message {
         contact_repository: ContactRepository,
         contact: Contact,
         client: Arc<Client>,
+        focused_message: Option<i64>,
     },
 
     CloseConversation(egui::ViewportId),
     ContactChatWindowFocused(Arc<String>),
+    ContactNameChanged(Arc<String>),
 }
 
 pub struct MainWindow {
@@ -64,29 +75,67 @@ pub struct MainWindow {
     dialog_window_text: Option<String>,
     conversations: HashMap<egui::ViewportId, conversation::Conversation>,
     handle: Handle,
-    sqlite: Sqlite,
+    sqlite: Option<Sqlite>,
 }
 
 impl MainWindow {
     pub fn new(handle: Handle) -> Self {
         let (sender, receiver) = mpsc::channel();
-        let sqlite = Sqlite::new().expect("Could not create database");
+        let (sqlite, screen, error) = match unlock_database::open_database() {
+            Ok(OpenedDatabase::Opened(sqlite)) => {
+                let sqlite = Some(sqlite);
+                let screen = Self::sign_in_screen(&sqlite, &sender, &handle);
+                (sqlite, screen, None)
+            }
+
+            Ok(OpenedDatabase::NeedsPassphrase(keyring_error)) => (
+                None,
+                Screen::UnlockDatabase(unlock_database::UnlockDatabase::new(
+                    keyring_error,
+                    sender.clone(),
+                    handle.clone(),
+                )),
+                None,
+            ),
+
+            Err(error) => (
+                None,
+                Screen::DatabaseError,
+                Some(format!("Could not open the database: {error:#}")),
+            ),
+        };
 
         Self {
-            screen: Screen::SignIn(sign_in::SignIn::new(
-                handle.clone(),
-                sqlite.clone(),
-                sender.clone(),
-            )),
+            screen,
             sender,
             receiver,
             personal_settings_window: None,
-            dialog_window_text: None,
+            dialog_window_text: error,
             conversations: HashMap::new(),
             handle,
             sqlite,
         }
     }
+
+    fn sign_in_screen(
+        sqlite: &Option<Sqlite>,
+        sender: &mpsc::Sender<Message>,
+        handle: &Handle,
+    ) -> Screen {
+        if let Some(sqlite) = sqlite {
+            Screen::SignIn(sign_in::SignIn::new(
+                handle.clone(),
+                sqlite.clone(),
+                sender.clone(),
+            ))
+        } else {
+            Screen::UnlockDatabase(unlock_database::UnlockDatabase::new(
+                None,
+                sender.clone(),
+                handle.clone(),
+            ))
+        }
+    }
 }
 
 impl eframe::App for MainWindow {
@@ -100,12 +149,23 @@ impl eframe::App for MainWindow {
 
         if let Ok(message) = self.receiver.try_recv() {
             match message {
+                Message::DatabaseUnlocked(sqlite) => {
+                    self.sqlite = Some(sqlite);
+                    self.screen = Self::sign_in_screen(&self.sqlite, &self.sender, &self.handle);
+                }
+
                 Message::SignIn(sign_in_return) => {
+                    let Some(sqlite) = self.sqlite.clone() else {
+                        return;
+                    };
+
                     let client = sign_in_return.client.clone();
+                    let _ = sqlite.claim_unowned_messages(&sign_in_return.email);
+
                     self.screen = Screen::Contacts(Box::new(contacts::Contacts::new(
                         sign_in_return,
                         self.sender.clone(),
-                        self.sqlite.clone(),
+                        sqlite,
                         self.handle.clone(),
                     )));
 
@@ -129,12 +189,21 @@ impl eframe::App for MainWindow {
                     ));
                 }
 
+                Message::BrowseOffline(email) => {
+                    if let Some(sqlite) = self.sqlite.clone() {
+                        self.screen = Screen::OfflineContacts(Box::new(
+                            offline_contacts::OfflineContacts::new(
+                                email,
+                                self.sender.clone(),
+                                sqlite,
+                                self.handle.clone(),
+                            ),
+                        ));
+                    }
+                }
+
                 Message::SignOut => {
-                    self.screen = Screen::SignIn(sign_in::SignIn::new(
-                        self.handle.clone(),
-                        self.sqlite.clone(),
-                        self.sender.clone(),
-                    ));
+                    self.screen = Self::sign_in_screen(&self.sqlite, &self.sender, &self.handle);
                 }
 
                 Message::OpenPersonalSettings(
@@ -155,6 +224,7 @@ impl eframe::App for MainWindow {
                                 display_name,
                                 client,
                                 contact_repository,
+                                self.sqlite.clone(),
                                 self.sender.clone(),
                                 contacts_sender,
                                 blp_bl,
@@ -173,22 +243,16 @@ impl eframe::App for MainWindow {
 
                 Message::NotificationServerEvent(event) => {
                     if let msnp11_sdk::Event::Disconnected = event {
-                        self.screen = Screen::SignIn(sign_in::SignIn::new(
-                            self.handle.clone(),
-                            self.sqlite.clone(),
-                            self.sender.clone(),
-                        ));
+                        self.screen =
+                            Self::sign_in_screen(&self.sqlite, &self.sender, &self.handle);
 
                         self.dialog_window_text = Some("Lost connection to the server".to_string());
                         ui.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
                             egui::UserAttentionType::Informational,
                         ));
                     } else if let msnp11_sdk::Event::LoggedInAnotherDevice = event {
-                        self.screen = Screen::SignIn(sign_in::SignIn::new(
-                            self.handle.clone(),
-                            self.sqlite.clone(),
-                            self.sender.clone(),
-                        ));
+                        self.screen =
+                            Self::sign_in_screen(&self.sqlite, &self.sender, &self.handle);
 
                         self.dialog_window_text = Some(
                             "Disconnected as you have signed in on another computer".to_string(),
@@ -250,6 +314,12 @@ impl eframe::App for MainWindow {
                     }
                 }
 
+                Message::ContactNameChanged(email) => {
+                    for conversation in self.conversations.values_mut() {
+                        conversation.handle_event(Message::ContactNameChanged(email.clone()), ui);
+                    }
+                }
+
                 Message::ContactDisplayPictureEvent { email, data } => {
                     if let Screen::Contacts(contacts) = &mut self.screen {
                         contacts.handle_event(
@@ -300,20 +370,26 @@ impl eframe::App for MainWindow {
                     contact_repository,
                     contact,
                     client,
+                    focused_message,
                 } => {
-                    if let Some((id, _)) = self.conversations.iter().find(|(_, conversation)| {
-                        conversation.get_participants().contains_key(&contact.email)
-                            || conversation.get_participants().is_empty()
-                                && conversation
-                                    .get_last_participant()
-                                    .as_ref()
-                                    .is_some_and(|participant| participant.email == contact.email)
-                    }) {
+                    if let Some((id, conversation)) =
+                        self.conversations.iter_mut().find(|(_, conversation)| {
+                            conversation.get_participants().contains_key(&contact.email)
+                                || conversation.get_participants().is_empty()
+                                    && conversation.get_last_participant().as_ref().is_some_and(
+                                        |participant| participant.email == contact.email,
+                                    )
+                        })
+                    {
+                        if let Some(message_id) = focused_message {
+                            conversation.focus_message(message_id);
+                        }
+
                         ui.send_viewport_cmd_to(*id, egui::ViewportCommand::Focus);
                         let _ = self
                             .sender
                             .send(Message::ContactChatWindowFocused(contact.email.clone()));
-                    } else {
+                    } else if let Some(sqlite) = &self.sqlite {
                         let viewport_id = egui::ViewportId::from_hash_of(contact.guid.clone());
                         self.conversations.insert(
                             viewport_id,
@@ -326,9 +402,10 @@ impl eframe::App for MainWindow {
                                 contact_repository,
                                 client,
                                 self.sender.clone(),
-                                self.sqlite.clone(),
+                                sqlite.clone(),
                                 self.handle.clone(),
                                 viewport_id,
+                                focused_message,
                             ),
                         );
                     }
@@ -350,9 +427,19 @@ impl eframe::App for MainWindow {
             }
         }
 
+        if let Screen::Contacts(contacts) = &mut self.screen {
+            contacts.send_outbox_messages(ui, &self.conversations);
+        }
+
         match &mut self.screen {
+            Screen::DatabaseError => {
+                egui::CentralPanel::default().show_inside(ui, |_| ());
+            }
+
+            Screen::UnlockDatabase(unlock_database) => unlock_database.ui(ui, frame),
             Screen::SignIn(sign_in) => sign_in.ui(ui, frame),
             Screen::Contacts(contacts) => contacts.ui(ui, frame),
+            Screen::OfflineContacts(offline_contacts) => offline_contacts.ui(ui, frame),
         }
 
         if self.dialog_window_text.is_some() {
@@ -386,6 +473,15 @@ impl eframe::App for MainWindow {
                     if ui.input(|i| i.viewport().close_requested()) {
                         self.dialog_window_text = None;
                     }
+
+                    if self.dialog_window_text.is_none()
+                        && let Screen::DatabaseError = self.screen
+                    {
+                        ui.send_viewport_cmd_to(
+                            egui::ViewportId::ROOT,
+                            egui::ViewportCommand::Close,
+                        );
+                    }
                 },
             );
         }
@@ -0,0 +1,397 @@
+use crate::models::transcript::participants_key;
+use anyhow::{Context, bail};
+use r2d2_sqlite::rusqlite;
+use r2d2_sqlite::rusqlite::fallible_streaming_iterator::FallibleStreamingIterator;
+use std::path::Path;
+
+struct Migration {
+    // The database is backed up before running migrations that drop or rewrite data
+    destructive: bool,
+    apply: fn(&rusqlite::Transaction) -> rusqlite::Result<()>,
+}
+
+// Migration n brings the database to user_version n + 1. Only ever append to this list.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        destructive: false,
+        apply: create_tables,
+    },
+    Migration {
+        destructive: false,
+        apply: add_message_timestamps,
+    },
+    Migration {
+        destructive: false,
+        apply: create_messages_search_index,
+    },
+    Migration {
+        destructive: false,
+        apply: add_message_errored,
+    },
+    Migration {
+        destructive: true,
+        apply: add_message_owners,
+    },
+    Migration {
+        destructive: false,
+        apply: add_display_picture_last_seen,
+    },
+    Migration {
+        destructive: false,
+        apply: add_message_colors,
+    },
+    Migration {
+        destructive: false,
+        apply: add_message_font_faces,
+    },
+    Migration {
+        destructive: false,
+        apply: create_outbox,
+    },
+    Migration {
+        destructive: false,
+        apply: create_contacts,
+    },
+    Migration {
+        destructive: true,
+        apply: add_message_participants,
+    },
+    Migration {
+        destructive: false,
+        apply: create_contact_aliases,
+    },
+    Migration {
+        destructive: false,
+        apply: create_contact_alerts,
+    },
+    Migration {
+        destructive: false,
+        apply: create_presence_history,
+    },
+    Migration {
+        destructive: false,
+        apply: add_outbox_sending,
+    },
+    Migration {
+        destructive: false,
+        apply: add_contact_friendly_names,
+    },
+];
+
+pub fn run_migrations(conn: &mut rusqlite::Connection, database_path: &Path) -> anyhow::Result<()> {
+    let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let version = version as usize;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "The database was created by a newer version of meowsn (schema version {version}, \
+            this version supports up to {})",
+            MIGRATIONS.len()
+        );
+    }
+
+    // A brand new database has nothing worth backing up
+    let has_tables: bool =
+        conn.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master)", [], |row| {
+            row.get(0)
+        })?;
+
+    let pending = &MIGRATIONS[version..];
+    if has_tables && pending.iter().any(|migration| migration.destructive) {
+        let backup_path = database_path.with_extension(format!("v{version}.db.bak"));
+        if backup_path.exists() {
+            std::fs::remove_file(&backup_path)?;
+        }
+
+        conn.execute(
+            "VACUUM INTO ?1",
+            [backup_path.to_string_lossy().to_string()],
+        )
+        .context("Could not back up the database before migrating")?;
+    }
+
+    for (index, migration) in pending.iter().enumerate() {
+        let new_version = version + index + 1;
+        let tx = conn.transaction()?;
+
+        (migration.apply)(&tx)
+            .with_context(|| format!("Could not migrate database to version {new_version}"))?;
+
+        tx.pragma_update(None, "user_version", new_version as u32)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn create_tables(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS display_pictures (\
+            id INTEGER PRIMARY KEY,\
+            picture BLOB NOT NULL,\
+            hash TEXT UNIQUE NOT NULL\
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS users (\
+            id INTEGER PRIMARY KEY,\
+            email TEXT UNIQUE NOT NULL,\
+            personal_message TEXT,\
+            display_picture_id INTEGER,\
+            FOREIGN KEY (display_picture_id) REFERENCES display_pictures (id)\
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS messages (\
+            id INTEGER PRIMARY KEY,\
+            sender TEXT NOT NULL,\
+            receiver TEXT,\
+            is_nudge BOOL NOT NULL,\
+            text TEXT NOT NULL,\
+            bold BOOL NOT NULL,\
+            italic BOOL NOT NULL,\
+            underline BOOL NOT NULL,\
+            strikethrough BOOL NOT NULL,\
+            session_id TEXT\
+        )",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_message_timestamps(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    // Databases from before versioning may already have this column
+    let mut stmt =
+        tx.prepare("SELECT name FROM pragma_table_info('messages') WHERE name = 'timestamp'")?;
+
+    if stmt.query([])?.count()? == 0 {
+        // Older messages were never timestamped, so they keep 0 and are shown with an unknown date
+        tx.execute(
+            "ALTER TABLE messages ADD COLUMN timestamp INTEGER NOT NULL DEFAULT 0",
+            (),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn create_messages_search_index(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE messages_fts USING fts5(text, content='messages', content_rowid='id');
+
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (rowid, text) VALUES (new.id, new.text);
+        END;
+
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, text) VALUES ('delete', old.id, old.text);
+        END;
+
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF text ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, text) VALUES ('delete', old.id, old.text);
+            INSERT INTO messages_fts (rowid, text) VALUES (new.id, new.text);
+        END;
+
+        INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');",
+    )
+}
+
+fn add_message_errored(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE messages ADD COLUMN errored BOOL NOT NULL DEFAULT FALSE",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_message_owners(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE messages ADD COLUMN owner TEXT", ())?;
+
+    // Rows never recorded which account stored them, so guess from the accounts that signed in
+    // on this machine, preferring the sender
+    tx.execute_batch(
+        "UPDATE messages SET owner = sender WHERE sender IN (SELECT email FROM users);
+
+        UPDATE messages SET owner = receiver
+        WHERE owner IS NULL AND receiver IN (SELECT email FROM users);
+
+        UPDATE messages SET owner = (SELECT email FROM users)
+        WHERE owner IS NULL AND (SELECT COUNT(*) FROM users) = 1;
+
+        CREATE INDEX messages_owner ON messages (owner, timestamp);",
+    )
+}
+
+fn add_display_picture_last_seen(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE display_pictures ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0",
+        (),
+    )?;
+
+    // Existing pictures get a full grace period instead of being collected straight away
+    tx.execute(
+        "UPDATE display_pictures SET last_seen = ?1",
+        [chrono::Local::now().timestamp()],
+    )?;
+
+    Ok(())
+}
+
+fn add_message_colors(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE messages ADD COLUMN color TEXT NOT NULL DEFAULT '0'",
+        (),
+    )?;
+
+    Ok(())
+}
+
+// Unused: msnp11-sdk can't send or receive font faces, so none are stored
+fn add_message_font_faces(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE messages ADD COLUMN font_face TEXT", ())?;
+    Ok(())
+}
+
+fn create_outbox(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE outbox (\
+            message_id INTEGER PRIMARY KEY,\
+            attempts INTEGER NOT NULL,\
+            next_attempt INTEGER NOT NULL,\
+            FOREIGN KEY (message_id) REFERENCES messages (id)\
+        );
+
+        CREATE TRIGGER messages_outbox_delete AFTER DELETE ON messages BEGIN
+            DELETE FROM outbox WHERE message_id = old.id;
+        END;",
+    )
+}
+
+fn create_contacts(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE contacts (\
+            owner TEXT NOT NULL,\
+            email TEXT NOT NULL,\
+            display_name TEXT NOT NULL,\
+            guid TEXT,\
+            lists TEXT NOT NULL,\
+            personal_message TEXT,\
+            display_picture_hash TEXT,\
+            PRIMARY KEY (owner, email)\
+        )",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_message_participants(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE messages ADD COLUMN participants TEXT", ())?;
+
+    // Group messages only recorded their session, so the best guess at who took part is everyone
+    // who spoke in it
+    let mut stmt = tx.prepare(
+        "SELECT owner, session_id, GROUP_CONCAT(DISTINCT sender) FROM messages \
+        WHERE session_id IS NOT NULL GROUP BY owner, session_id",
+    )?;
+
+    let sessions = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<usize, Option<String>>(0)?,
+                row.get::<usize, String>(1)?,
+                row.get::<usize, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (owner, session_id, senders) in sessions {
+        let participants =
+            participants_key(senders.split(','), owner.as_deref().unwrap_or_default());
+
+        tx.execute(
+            "UPDATE messages SET participants = ?1 WHERE owner IS ?2 AND session_id = ?3",
+            rusqlite::params![participants, owner, session_id],
+        )?;
+    }
+
+    tx.execute(
+        "CREATE INDEX messages_participants ON messages (owner, participants, timestamp)",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn create_contact_aliases(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE contact_aliases (\
+            owner TEXT NOT NULL,\
+            email TEXT NOT NULL,\
+            alias TEXT NOT NULL,\
+            PRIMARY KEY (owner, email)\
+        )",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn create_contact_alerts(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE contact_alerts (\
+            owner TEXT NOT NULL,\
+            email TEXT NOT NULL,\
+            on_sign_in BOOLEAN NOT NULL,\
+            on_sign_out BOOLEAN NOT NULL,\
+            on_status_change BOOLEAN NOT NULL,\
+            on_personal_message BOOLEAN NOT NULL,\
+            notify BOOLEAN NOT NULL,\
+            request_attention BOOLEAN NOT NULL,\
+            highlight BOOLEAN NOT NULL,\
+            PRIMARY KEY (owner, email)\
+        )",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn create_presence_history(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE presence_history (\
+            id INTEGER PRIMARY KEY,\
+            owner TEXT NOT NULL,\
+            email TEXT NOT NULL,\
+            status TEXT,\
+            timestamp INTEGER NOT NULL\
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE INDEX presence_history_contact ON presence_history (owner, email, timestamp)",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_outbox_sending(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE outbox ADD COLUMN sending BOOL NOT NULL DEFAULT FALSE",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_contact_friendly_names(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE contacts ADD COLUMN friendly_name TEXT", ())?;
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct AddedByRequest {
+    pub email: Arc<String>,
+    pub display_name: Arc<String>,
+}
@@ -0,0 +1,19 @@
+// What to watch a contact for, and how to tell the user about it
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct AlertRule {
+    pub on_sign_in: bool,
+    pub on_sign_out: bool,
+    pub on_status_change: bool,
+    pub on_personal_message: bool,
+    pub notify: bool,
+    pub request_attention: bool,
+    pub highlight: bool,
+}
+
+impl AlertRule {
+    // A rule that watches nothing or does nothing when triggered isn't worth keeping
+    pub fn is_active(&self) -> bool {
+        (self.on_sign_in || self.on_sign_out || self.on_status_change || self.on_personal_message)
+            && (self.notify || self.request_attention || self.highlight)
+    }
+}
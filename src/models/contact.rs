@@ -1,15 +1,52 @@
 use crate::models::display_picture::DisplayPicture;
-use msnp11_sdk::{MsnpList, Presence};
+use msnp11_sdk::{MsnpList, MsnpStatus, Presence};
 use std::sync::Arc;
 
 #[derive(Clone, Default)]
 pub struct Contact {
     pub email: Arc<String>,
     pub display_name: Arc<String>,
+    pub alias: Option<Arc<String>>,
+    // The name the user renamed the contact to on the server, which presence updates don't change
+    pub friendly_name: Option<Arc<String>>,
     pub guid: Option<Arc<String>>,
     pub lists: Vec<MsnpList>,
+    pub groups: Vec<Arc<String>>,
     pub status: Option<Arc<Presence>>,
     pub personal_message: Option<Arc<String>>,
+    pub current_media: Option<Arc<String>>,
     pub display_picture: Option<DisplayPicture>,
     pub opening_conversation: bool,
 }
+
+impl Contact {
+    // The alias the user gave the contact, falling back to the name they renamed it to and then to
+    // the name the contact chose
+    pub fn name(&self) -> &Arc<String> {
+        self.alias
+            .as_ref()
+            .or(self.friendly_name.as_ref())
+            .unwrap_or(&self.display_name)
+    }
+
+    // The name the contact chose, if another one is shown in its place
+    pub fn real_name(&self) -> Option<&Arc<String>> {
+        Some(&self.display_name).filter(|display_name| *display_name != self.name())
+    }
+
+    pub fn status_text(&self) -> &'static str {
+        match &self.status {
+            Some(presence) => match presence.status {
+                MsnpStatus::Busy => "Busy",
+                MsnpStatus::OnThePhone => "On the Phone",
+                MsnpStatus::Away => "Away",
+                MsnpStatus::BeRightBack => "Be Right Back",
+                MsnpStatus::OutToLunch => "Out to Lunch",
+                MsnpStatus::Idle => "Idle",
+                _ => "Online",
+            },
+
+            None => "Offline",
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Group {
+    // The server doesn't tell us the guid of a group created this session until the next sign in
+    pub guid: Option<Arc<String>>,
+    pub name: Arc<String>,
+}
@@ -0,0 +1,8 @@
+pub struct HistoryStats {
+    pub message_count: u32,
+    pub sent_count: u32,
+    pub nudge_count: u32,
+    pub group_message_count: u32,
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+}
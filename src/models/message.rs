@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 #[derive(Clone, Default)]
 pub struct Message {
+    pub id: Option<i64>,
     pub sender: Arc<String>,
     pub receiver: Option<Arc<String>>,
     pub is_nudge: bool,
@@ -11,7 +12,9 @@ pub struct Message {
     pub underline: bool,
     pub strikethrough: bool,
     pub session_id: Option<Arc<String>>,
+    pub participants: Option<Arc<String>>,
     pub color: String,
     pub is_history: bool,
     pub errored: bool,
+    pub timestamp: i64,
 }
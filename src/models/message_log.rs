@@ -0,0 +1,24 @@
+pub struct LogEntry {
+    pub from: String,
+    pub to: Vec<String>,
+    pub session_id: String,
+    pub timestamp: i64,
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub color: String,
+}
+
+pub struct FriendlyName {
+    pub name: String,
+    pub is_user: bool,
+}
+
+pub struct MessageLog {
+    pub file_name: String,
+    pub contact_email: String,
+    pub friendly_names: Vec<FriendlyName>,
+    pub entries: Vec<LogEntry>,
+}
@@ -1,8 +1,17 @@
+pub mod added_by_request;
+pub mod alert_rule;
 pub mod config;
 pub mod contact;
 pub mod display_picture;
+pub mod group;
+pub mod history_stats;
 pub mod message;
+pub mod message_log;
+pub mod outbox_entry;
+pub mod presence_entry;
+pub mod search_result;
 pub mod sign_in_return;
 pub mod switchboard_and_participants;
 pub mod tab;
+pub mod transcript;
 pub mod user;
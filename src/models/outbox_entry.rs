@@ -0,0 +1,8 @@
+use crate::models::message::Message;
+
+pub struct OutboxEntry {
+    pub message: Message,
+    pub attempts: u32,
+    pub next_attempt: i64,
+    pub sending: bool,
+}
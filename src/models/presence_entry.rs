@@ -0,0 +1,8 @@
+use msnp11_sdk::MsnpStatus;
+
+// A contact's presence from this moment on, None meaning they went offline or the user stopped
+// seeing them by signing out
+pub struct PresenceEntry {
+    pub status: Option<MsnpStatus>,
+    pub timestamp: i64,
+}
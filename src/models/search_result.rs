@@ -0,0 +1,6 @@
+use crate::models::message::Message;
+
+pub struct SearchResult {
+    pub message: Message,
+    pub snippet: String,
+}
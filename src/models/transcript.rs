@@ -0,0 +1,33 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq)]
+pub enum TranscriptKind {
+    Contact(Arc<String>),
+    // Keyed by the other participants, see participants_key
+    Group(Arc<String>),
+}
+
+#[derive(Clone)]
+pub struct Transcript {
+    pub kind: TranscriptKind,
+    pub participants: Vec<Arc<String>>,
+    pub message_count: u32,
+    pub last_timestamp: i64,
+}
+
+// Identifies a group conversation by everyone in it besides the user, so it doesn't matter who
+// joined first or which session the messages were sent in
+pub fn participants_key<T: AsRef<str>>(
+    participants: impl IntoIterator<Item = T>,
+    user_email: &str,
+) -> String {
+    participants
+        .into_iter()
+        .filter(|participant| participant.as_ref() != user_email)
+        .map(|participant| participant.as_ref().to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,158 @@
+use crate::models::added_by_request::AddedByRequest;
+use crate::screens::contacts::contacts;
+use eframe::egui;
+use egui_taffy::taffy::prelude::{auto, length, percent};
+use egui_taffy::{TuiBuilderLogic, taffy, tui};
+use std::sync::mpsc;
+use tokio::runtime::Handle;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Answer {
+    AddToList,
+    Allow,
+    Block,
+}
+
+// Asks what to do about someone who added the user, one request at a time
+pub struct AddedBy {
+    request: AddedByRequest,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+}
+
+impl AddedBy {
+    pub fn new(request: AddedByRequest, contacts_sender: mpsc::Sender<contacts::Message>) -> Self {
+        Self {
+            request,
+            contacts_sender,
+        }
+    }
+
+    pub fn added_by(&mut self, ui: &mut egui::Ui, pending: usize) {
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame {
+                    fill: ui.visuals().window_fill,
+                    ..Default::default()
+                }
+                .inner_margin(5.),
+            )
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("added-by-screen"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
+                        align_items: Some(taffy::AlignItems::Stretch),
+                        size: taffy::Size {
+                            width: percent(0.9),
+                            height: auto(),
+                        },
+                        padding: length(20.),
+                        gap: length(15.),
+                        ..Default::default()
+                    })
+                    .show(|tui| {
+                        tui.ui(|ui| {
+                            ui.label(format!(
+                                "{} <{}> has added you to their contact list.",
+                                self.request.display_name, self.request.email
+                            ));
+
+                            ui.add_space(3.);
+                            ui.label(
+                                "Do you want to add them to your list, only let them see your \
+                                status and message you, or block them?",
+                            );
+                            if pending > 1 {
+                                ui.add_space(3.);
+                                ui.weak(format!("{} more requests are waiting.", pending - 1));
+                            }
+                        });
+
+                        tui.style(taffy::Style {
+                            align_self: Some(taffy::AlignItems::Center),
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Add to my list").clicked() {
+                                    self.answer(Answer::AddToList);
+                                }
+
+                                if ui.button("Allow").clicked() {
+                                    self.answer(Answer::Allow);
+                                }
+
+                                if ui.button("Block").clicked() {
+                                    self.answer(Answer::Block);
+                                }
+
+                                if ui
+                                    .button("Decide Later")
+                                    .on_hover_text("Keep this request for later")
+                                    .clicked()
+                                {
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::DismissAddedBy);
+                                }
+                            })
+                        })
+                    })
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self.contacts_sender.send(contacts::Message::DismissAddedBy);
+        }
+    }
+
+    fn answer(&self, answer: Answer) {
+        let _ = self.contacts_sender.send(contacts::Message::AnswerAddedBy(
+            self.request.email.clone(),
+            answer,
+        ));
+    }
+}
+
+// Notification actions only work with freedesktop notification servers, everywhere else the
+// request is answered from the dialog
+pub fn notify(
+    request: &AddedByRequest,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+) {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary("Someone has added you").body(&format!(
+        "{} <{}> has added you to their contact list",
+        request.display_name, request.email
+    ));
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        notification
+            .action("add", "Add to my list")
+            .action("allow", "Allow")
+            .action("block", "Block");
+
+        let email = request.email.clone();
+        handle.spawn_blocking(move || {
+            if let Ok(shown) = notification.show() {
+                shown.wait_for_action(|action| {
+                    let answer = match action {
+                        "add" => Answer::AddToList,
+                        "allow" => Answer::Allow,
+                        "block" => Answer::Block,
+                        _ => return,
+                    };
+
+                    let _ = contacts_sender.send(contacts::Message::AnswerAddedBy(email, answer));
+                });
+            }
+        });
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = (contacts_sender, handle);
+        let _ = notification.show();
+    }
+}
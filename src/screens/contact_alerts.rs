@@ -0,0 +1,108 @@
+use crate::models::alert_rule::AlertRule;
+use crate::models::contact::Contact;
+use crate::screens::contacts::contacts;
+use eframe::egui;
+use std::sync::mpsc;
+
+// Edits what the user is alerted about for a single contact
+pub struct ContactAlerts {
+    rule: AlertRule,
+    contact: Contact,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+}
+
+impl ContactAlerts {
+    pub fn new(
+        contact: Contact,
+        rule: Option<AlertRule>,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+    ) -> Self {
+        Self {
+            rule: rule.unwrap_or(AlertRule {
+                on_sign_in: true,
+                notify: true,
+                ..Default::default()
+            }),
+            contact,
+            contacts_sender,
+        }
+    }
+
+    pub fn contact_alerts(&mut self, ui: &mut egui::Ui) {
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(15),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                ui.label(format!(
+                    "Alert me about {} <{}> when they:",
+                    self.contact.name(),
+                    self.contact.email
+                ));
+
+                ui.add_space(3.);
+                ui.indent("alert_events", |ui| {
+                    ui.checkbox(&mut self.rule.on_sign_in, "Sign in");
+                    ui.checkbox(&mut self.rule.on_sign_out, "Sign out");
+                    ui.checkbox(&mut self.rule.on_status_change, "Change their status");
+                    ui.checkbox(
+                        &mut self.rule.on_personal_message,
+                        "Change their personal message",
+                    );
+                });
+
+                ui.add_space(5.);
+                ui.label("By:");
+                ui.add_space(3.);
+                ui.indent("alert_actions", |ui| {
+                    ui.checkbox(&mut self.rule.notify, "Showing a desktop notification");
+                    ui.checkbox(
+                        &mut self.rule.request_attention,
+                        "Flashing the contact list window",
+                    );
+
+                    ui.checkbox(&mut self.rule.highlight, "Highlighting them in the list")
+                        .on_hover_text("The highlight goes away once you click on them");
+                });
+
+                ui.add_space(10.);
+                ui.horizontal(|ui| {
+                    if ui.button("Ok").clicked() {
+                        let _ = self.contacts_sender.send(contacts::Message::SetAlertRule(
+                            self.contact.email.clone(),
+                            Some(self.rule).filter(|rule| rule.is_active()),
+                        ));
+
+                        let _ = self
+                            .contacts_sender
+                            .send(contacts::Message::CloseContactAlerts);
+                    }
+
+                    if ui.button("Remove Alerts").clicked() {
+                        let _ = self.contacts_sender.send(contacts::Message::SetAlertRule(
+                            self.contact.email.clone(),
+                            None,
+                        ));
+
+                        let _ = self
+                            .contacts_sender
+                            .send(contacts::Message::CloseContactAlerts);
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        let _ = self
+                            .contacts_sender
+                            .send(contacts::Message::CloseContactAlerts);
+                    }
+                });
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::CloseContactAlerts);
+        }
+    }
+}
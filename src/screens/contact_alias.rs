@@ -0,0 +1,105 @@
+use crate::models::contact::Contact;
+use crate::screens::contacts::contacts;
+use eframe::egui;
+use egui_taffy::taffy::prelude::{auto, length, percent};
+use egui_taffy::{TuiBuilderLogic, taffy, tui};
+use std::sync::{Arc, mpsc};
+
+// Edits the name only this client shows for a contact
+pub struct ContactAlias {
+    alias: String,
+    contact: Contact,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+}
+
+impl ContactAlias {
+    pub fn new(contact: Contact, contacts_sender: mpsc::Sender<contacts::Message>) -> Self {
+        Self {
+            alias: contact
+                .alias
+                .as_ref()
+                .map(|alias| alias.to_string())
+                .unwrap_or_default(),
+            contact,
+            contacts_sender,
+        }
+    }
+
+    pub fn contact_alias(&mut self, ui: &mut egui::Ui) {
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame {
+                    fill: ui.visuals().window_fill,
+                    ..Default::default()
+                }
+                .inner_margin(5.),
+            )
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("contact-alias-screen"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
+                        align_items: Some(taffy::AlignItems::Stretch),
+                        size: taffy::Size {
+                            width: percent(0.9),
+                            height: auto(),
+                        },
+                        padding: length(20.),
+                        gap: length(15.),
+                        ..Default::default()
+                    })
+                    .show(|tui| {
+                        tui.ui(|ui| {
+                            let label = ui.label(format!(
+                                "Alias for {} <{}> (leave empty to use their own name):",
+                                self.contact.display_name, self.contact.email
+                            ));
+
+                            ui.add_space(3.);
+                            ui.add(
+                                egui::text_edit::TextEdit::singleline(&mut self.alias)
+                                    .hint_text("Alias")
+                                    .min_size(egui::Vec2::new(340., 5.)),
+                            )
+                            .labelled_by(label.id);
+                        });
+
+                        tui.style(taffy::Style {
+                            align_self: Some(taffy::AlignItems::Center),
+                            size: taffy::Size {
+                                width: percent(0.2),
+                                height: auto(),
+                            },
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Ok").clicked() {
+                                    let alias = self.alias.trim();
+                                    let _ = self.contacts_sender.send(contacts::Message::SetAlias(
+                                        self.contact.email.clone(),
+                                        (!alias.is_empty()).then(|| Arc::new(alias.to_string())),
+                                    ));
+
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseContactAlias);
+                                }
+
+                                if ui.button("Cancel").clicked() {
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseContactAlias);
+                                }
+                            })
+                        })
+                    })
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::CloseContactAlias);
+        }
+    }
+}
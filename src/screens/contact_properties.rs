@@ -0,0 +1,537 @@
+use crate::helpers::current_media::format_current_media;
+use crate::helpers::run_future::run_future;
+use crate::models::contact::Contact;
+use crate::models::display_picture::DisplayPicture;
+use crate::models::group::Group;
+use crate::models::history_stats::HistoryStats;
+use crate::models::presence_entry::PresenceEntry;
+use crate::screens::contacts::contacts;
+use crate::sqlite::Sqlite;
+use crate::svg;
+use chrono::{Local, NaiveDate, TimeZone};
+use eframe::egui;
+use msnp11_sdk::{Client, MsnpList, MsnpStatus};
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+const TIMELINE_DAYS: u64 = 7;
+
+// Shows everything known about a contact. The contact itself is passed in every frame so the
+// window follows presence and list changes while it's open
+pub struct ContactProperties {
+    email: Arc<String>,
+    stats: Option<HistoryStats>,
+    presence_history: Vec<PresenceEntry>,
+    cached_picture: Option<DisplayPicture>,
+    client: Arc<Client>,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+}
+
+impl ContactProperties {
+    pub fn new(
+        user_email: &str,
+        contact: &Contact,
+        sqlite: &Sqlite,
+        client: Arc<Client>,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+        handle: Handle,
+    ) -> Self {
+        // Contacts not talked to this session only have their picture in the cache
+        let cached_picture = if let Some(status) = &contact.status
+            && let Some(msn_object) = &status.msn_object
+            && let Ok(data) = sqlite.select_display_picture_data(&msn_object.sha1d)
+        {
+            Some(DisplayPicture {
+                data,
+                hash: Arc::new(msn_object.sha1d.clone()),
+            })
+        } else {
+            None
+        };
+
+        let timeline_start = Local::now()
+            .date_naive()
+            .checked_sub_days(chrono::Days::new(TIMELINE_DAYS - 1))
+            .and_then(local_midnight)
+            .unwrap_or_default();
+
+        Self {
+            email: contact.email.clone(),
+            stats: sqlite.select_history_stats(user_email, &contact.email).ok(),
+            presence_history: sqlite
+                .select_presence_history(user_email, &contact.email, timeline_start)
+                .unwrap_or_default(),
+            cached_picture,
+            client,
+            contacts_sender,
+            handle,
+        }
+    }
+
+    pub fn email(&self) -> &Arc<String> {
+        &self.email
+    }
+
+    pub fn contact_properties(
+        &mut self,
+        ui: &mut egui::Ui,
+        contact: Option<&Contact>,
+        groups: &[Group],
+        last_seen: Option<i64>,
+    ) {
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(15),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                let Some(contact) = contact else {
+                    ui.label(format!("{} is no longer in your contact list.", self.email));
+                    ui.add_space(5.);
+                    if ui.button("Close").clicked() {
+                        let _ = self
+                            .contacts_sender
+                            .send(contacts::Message::CloseContactProperties);
+                    }
+
+                    return;
+                };
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            let picture = contact
+                                .display_picture
+                                .clone()
+                                .or_else(|| self.cached_picture.clone());
+
+                            ui.add(if let Some(picture) = picture {
+                                egui::Image::from_bytes(
+                                    format!("bytes://{}", picture.hash),
+                                    picture.data,
+                                )
+                                .max_size(egui::Vec2::splat(200.))
+                                .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
+                                .alt_text(format!("Display picture for {}", contact.name()))
+                            } else {
+                                egui::Image::new(svg::default_display_picture())
+                                    .fit_to_exact_size(egui::Vec2::splat(96.))
+                                    .alt_text("Default display picture")
+                            });
+
+                            ui.vertical(|ui| {
+                                ui.heading(contact.name().as_str());
+                                ui.label(&*contact.email);
+                                ui.add_space(5.);
+
+                                egui::Grid::new("contact_properties_grid")
+                                    .num_columns(2)
+                                    .spacing([15., 5.])
+                                    .show(ui, |ui| {
+                                        ui.label("Name:");
+                                        ui.label(&*contact.display_name);
+                                        ui.end_row();
+
+                                        ui.label("Renamed to:");
+                                        ui.label(
+                                            contact
+                                                .friendly_name
+                                                .as_ref()
+                                                .map(|friendly_name| friendly_name.as_str())
+                                                .unwrap_or("None"),
+                                        );
+                                        ui.end_row();
+
+                                        ui.label("Alias:");
+                                        ui.label(
+                                            contact
+                                                .alias
+                                                .as_ref()
+                                                .map(|alias| alias.as_str())
+                                                .unwrap_or("None"),
+                                        );
+                                        ui.end_row();
+
+                                        ui.label("Status:");
+                                        ui.label(contact.status_text());
+                                        ui.end_row();
+
+                                        if contact.status.is_none() {
+                                            ui.label("Last seen:");
+                                            ui.label(date_text(last_seen));
+                                            ui.end_row();
+                                        }
+
+                                        ui.label("Personal message:");
+                                        ui.label(
+                                            contact
+                                                .personal_message
+                                                .as_ref()
+                                                .filter(|psm| !psm.is_empty())
+                                                .map(|psm| psm.as_str())
+                                                .unwrap_or("None"),
+                                        );
+                                        ui.end_row();
+
+                                        ui.label("Current media:");
+                                        ui.label(
+                                            contact
+                                                .current_media
+                                                .as_ref()
+                                                .and_then(|media| format_current_media(media))
+                                                .unwrap_or("None".to_string()),
+                                        );
+                                        ui.end_row();
+
+                                        ui.label("Lists:");
+                                        ui.label(lists_text(&contact.lists));
+                                        ui.end_row();
+
+                                        ui.label("Groups:");
+                                        ui.label(groups_text(contact, groups));
+                                        ui.end_row();
+
+                                        ui.label("GUID:");
+                                        ui.label(
+                                            contact
+                                                .guid
+                                                .as_ref()
+                                                .map(|guid| guid.as_str())
+                                                .unwrap_or("None"),
+                                        );
+                                        ui.end_row();
+                                    });
+                            });
+                        });
+
+                        ui.add_space(5.);
+                        ui.separator();
+                        ui.strong("History");
+                        ui.add_space(3.);
+
+                        if let Some(stats) = &self.stats
+                            && stats.message_count + stats.group_message_count > 0
+                        {
+                            egui::Grid::new("contact_history_grid")
+                                .num_columns(2)
+                                .spacing([15., 5.])
+                                .show(ui, |ui| {
+                                    ui.label("Messages:");
+                                    ui.label(format!(
+                                        "{} ({} sent, {} received)",
+                                        stats.message_count,
+                                        stats.sent_count,
+                                        stats.message_count - stats.sent_count
+                                    ));
+                                    ui.end_row();
+
+                                    ui.label("Nudges:");
+                                    ui.label(stats.nudge_count.to_string());
+                                    ui.end_row();
+
+                                    ui.label("Sent in groups:");
+                                    ui.label(stats.group_message_count.to_string());
+                                    ui.end_row();
+
+                                    ui.label("First message:");
+                                    ui.label(date_text(stats.first_timestamp));
+                                    ui.end_row();
+
+                                    ui.label("Last message:");
+                                    ui.label(date_text(stats.last_timestamp));
+                                    ui.end_row();
+                                });
+                        } else {
+                            ui.label("No messages with this contact yet");
+                        }
+
+                        ui.add_space(5.);
+                        ui.separator();
+                        ui.strong("Presence");
+                        ui.add_space(3.);
+
+                        if self.presence_history.is_empty() {
+                            ui.label("Not seen online yet");
+                        } else {
+                            presence_timeline(ui, &self.presence_history);
+                        }
+
+                        ui.add_space(5.);
+                        ui.separator();
+                        ui.horizontal_wrapped(|ui| {
+                            if ui
+                                .add_enabled(
+                                    contact.status.is_some(),
+                                    egui::Button::new("Send an Instant Message"),
+                                )
+                                .on_disabled_hover_text("This contact is offline")
+                                .clicked()
+                            {
+                                let _ =
+                                    self.contacts_sender
+                                        .send(contacts::Message::OpenConversation(
+                                            contact.email.clone(),
+                                        ));
+                            }
+
+                            if ui
+                                .add_enabled(contact.guid.is_some(), egui::Button::new("Rename..."))
+                                .clicked()
+                            {
+                                let _ = self
+                                    .contacts_sender
+                                    .send(contacts::Message::OpenRenameContact(contact.clone()));
+                            }
+
+                            if ui.button("Set Alias...").clicked() {
+                                let _ = self
+                                    .contacts_sender
+                                    .send(contacts::Message::OpenContactAlias(contact.clone()));
+                            }
+
+                            let blocked = contact.lists.contains(&MsnpList::BlockList);
+                            if ui
+                                .button(if blocked { "Unblock" } else { "Block" })
+                                .clicked()
+                            {
+                                let client = self.client.clone();
+                                let email = contact.email.clone();
+                                let contact = contact.email.clone();
+
+                                if blocked {
+                                    run_future(
+                                        self.handle.clone(),
+                                        async move { client.unblock_contact(&email).await },
+                                        self.contacts_sender.clone(),
+                                        move |result| {
+                                            contacts::Message::UnblockResult(
+                                                contact.clone(),
+                                                result,
+                                            )
+                                        },
+                                    );
+                                } else {
+                                    run_future(
+                                        self.handle.clone(),
+                                        async move { client.block_contact(&email).await },
+                                        self.contacts_sender.clone(),
+                                        move |result| {
+                                            contacts::Message::BlockResult(contact.clone(), result)
+                                        },
+                                    );
+                                }
+                            }
+
+                            if ui.button("Export History...").clicked() {
+                                let _ = self.contacts_sender.send(
+                                    contacts::Message::ExportHistory(Some(contact.email.clone())),
+                                );
+                            }
+
+                            if ui.button("Close").clicked() {
+                                let _ = self
+                                    .contacts_sender
+                                    .send(contacts::Message::CloseContactProperties);
+                            }
+                        });
+                    });
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::CloseContactProperties);
+        }
+    }
+}
+
+fn lists_text(lists: &[MsnpList]) -> String {
+    let names: Vec<_> = lists
+        .iter()
+        .map(|list| match list {
+            MsnpList::ForwardList => "Forward",
+            MsnpList::AllowList => "Allow",
+            MsnpList::BlockList => "Block",
+            MsnpList::ReverseList => "Reverse",
+            MsnpList::PendingList => "Pending",
+        })
+        .collect();
+
+    if names.is_empty() {
+        "None".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn groups_text(contact: &Contact, groups: &[Group]) -> String {
+    let names: Vec<_> = groups
+        .iter()
+        .filter(|group| {
+            group
+                .guid
+                .as_ref()
+                .is_some_and(|guid| contact.groups.contains(guid))
+        })
+        .map(|group| group.name.as_str())
+        .collect();
+
+    if names.is_empty() {
+        "None".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn date_text(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|timestamp| Local.timestamp_opt(timestamp, 0).single())
+        .map(|date_time| date_time.format("%B %-d, %Y %H:%M").to_string())
+        .unwrap_or("Unknown".to_string())
+}
+
+fn local_midnight(date: NaiveDate) -> Option<i64> {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|date_time| date_time.timestamp())
+}
+
+fn status_color(status: &MsnpStatus) -> egui::Color32 {
+    match status {
+        MsnpStatus::Busy | MsnpStatus::OnThePhone => egui::Color32::from_rgb(230, 69, 83),
+        MsnpStatus::Online => egui::Color32::from_rgb(64, 160, 43),
+        _ => egui::Color32::from_rgb(223, 142, 29),
+    }
+}
+
+// Draws a bar per day, from midnight to midnight, filled in wherever the contact was online, and
+// a last one with how often they were online at each hour
+fn presence_timeline(ui: &mut egui::Ui, entries: &[PresenceEntry]) {
+    let now = Local::now().timestamp();
+    let today = Local::now().date_naive();
+    let mut online_hours = [0u32; 24];
+
+    for days_ago in 0..TIMELINE_DAYS {
+        let Some(date) = today.checked_sub_days(chrono::Days::new(days_ago)) else {
+            continue;
+        };
+
+        let (Some(start), Some(end)) = (
+            local_midnight(date),
+            date.succ_opt().and_then(local_midnight),
+        ) else {
+            continue;
+        };
+
+        // Each entry lasts until the next one, or until now for the last one
+        let periods: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let status = entry.status.as_ref()?;
+                let until = entries
+                    .get(index + 1)
+                    .map(|next| next.timestamp)
+                    .unwrap_or(now);
+
+                let from = entry.timestamp.max(start);
+                let to = until.min(end);
+                (from < to).then_some((from, to, status))
+            })
+            .collect();
+
+        for (hour, online) in online_hours.iter_mut().enumerate() {
+            let hour_start = start + hour as i64 * 60 * 60;
+            let hour_end = hour_start + 60 * 60;
+            if periods
+                .iter()
+                .any(|(from, to, _)| *from < hour_end && *to > hour_start)
+            {
+                *online += 1;
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_sized(
+                [90., 14.],
+                egui::Label::new(date.format("%a, %b %-d").to_string()),
+            );
+
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.), egui::Sense::hover());
+
+            let painter = ui.painter();
+            painter.rect_filled(rect, 2., ui.visuals().extreme_bg_color);
+            let x = |timestamp: i64| {
+                rect.left() + rect.width() * (timestamp - start) as f32 / (end - start) as f32
+            };
+
+            for (from, to, status) in &periods {
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges(x(*from)..=x(*to), rect.y_range()),
+                    0.,
+                    status_color(status),
+                );
+            }
+
+            response.on_hover_text(
+                periods
+                    .iter()
+                    .map(|(from, to, _)| {
+                        format!(
+                            "{} - {}",
+                            Local
+                                .timestamp_opt(*from, 0)
+                                .single()
+                                .map(|date_time| date_time.format("%H:%M").to_string())
+                                .unwrap_or_default(),
+                            Local
+                                .timestamp_opt(*to, 0)
+                                .single()
+                                .map(|date_time| date_time.format("%H:%M").to_string())
+                                .unwrap_or_default()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        });
+    }
+
+    ui.add_space(3.);
+    ui.horizontal(|ui| {
+        ui.add_sized([90., 14.], egui::Label::new("Usually"));
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.), egui::Sense::hover());
+
+        let painter = ui.painter();
+        painter.rect_filled(rect, 2., ui.visuals().extreme_bg_color);
+        let hour_width = rect.width() / 24.;
+        for (hour, online) in online_hours.iter().enumerate() {
+            if *online == 0 {
+                continue;
+            }
+
+            let left = rect.left() + hour as f32 * hour_width;
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(left..=left + hour_width, rect.y_range()),
+                0.,
+                status_color(&MsnpStatus::Online)
+                    .gamma_multiply(*online as f32 / TIMELINE_DAYS as f32),
+            );
+        }
+
+        response.on_hover_text("How many of the last days they were online at each hour");
+    });
+
+    ui.add_space(3.);
+    ui.horizontal(|ui| {
+        ui.colored_label(status_color(&MsnpStatus::Online), "■ Online");
+        ui.colored_label(status_color(&MsnpStatus::Away), "■ Away");
+        ui.colored_label(status_color(&MsnpStatus::Busy), "■ Busy");
+    });
+}
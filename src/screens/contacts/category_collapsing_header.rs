@@ -2,23 +2,39 @@ use crate::contact_repository::ContactRepository;
 use crate::helpers::run_future::run_future;
 use crate::models::contact::Contact;
 use crate::models::display_picture::DisplayPicture;
+use crate::models::group::Group;
 use crate::screens::contacts::contacts;
 use crate::screens::contacts::status_selector::Status;
 use crate::{main_window, svg};
+use chrono::{Local, TimeZone};
 use eframe::egui;
 use eframe::egui::text::LayoutJob;
 use eframe::egui::{FontId, TextFormat, Ui};
 use msnp11_sdk::{Client, MsnpList, MsnpStatus};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, mpsc};
 use tokio::runtime::Handle;
 
+// The server doesn't send the guid of a group created this session until the list is loaded again
+const NEW_GROUP_HINT: &str = "This group was created in this session. It can be managed once the \
+    contact list is loaded again at your next sign in.";
+
+// A contact map and the order to list it in. Categories list their sections one after the other
+pub type ContactSection<'a> = (&'a mut BTreeMap<Arc<String>, Contact>, &'a [Arc<String>]);
+
 #[allow(clippy::too_many_arguments)]
 pub fn category_collapsing_header(
     ui: &mut Ui,
     name: &str,
+    group: Option<&Group>,
+    groups: &[Group],
+    filter: impl Fn(&Contact) -> bool,
+    compact: bool,
     selected_contact: &mut Option<Arc<String>>,
-    contacts: &mut BTreeMap<Arc<String>, Contact>,
+    scroll_to_selected: &mut bool,
+    alerted_contacts: &mut HashMap<Arc<String>, String>,
+    last_seen: &HashMap<Arc<String>, i64>,
+    sections: &mut [ContactSection],
     main_window_sender: mpsc::Sender<main_window::Message>,
     contacts_sender: mpsc::Sender<contacts::Message>,
     handle: Handle,
@@ -29,159 +45,212 @@ pub fn category_collapsing_header(
     contact_repository: ContactRepository,
     client: Arc<Client>,
 ) {
-    if egui::CollapsingHeader::new(name)
+    let contact_count: usize = sections
+        .iter()
+        .map(|(contacts, _)| contacts.values().filter(|contact| filter(contact)).count())
+        .sum();
+
+    let header = egui::CollapsingHeader::new(name)
+        .id_salt((name, group.and_then(|group| group.guid.clone())))
         .default_open(true)
         .show(ui, |ui| {
-            if contacts.is_empty() {
+            if contact_count == 0 {
                 ui.label(" No contacts in this category");
             } else {
-                for contact in contacts.values_mut() {
-                    let user_email = user_email.clone();
-                    let user_display_name = user_display_name.clone();
-                    let user_display_picture = user_display_picture.clone();
-                    let contact_repository = contact_repository.clone();
-                    let client = client.clone();
+                for (contacts, order) in sections.iter_mut() {
+                    for email in order.iter() {
+                        let Some(contact) = contacts.get_mut(email) else {
+                            continue;
+                        };
 
-                    ui.horizontal(|ui| {
-                        let mut alt_text = "Contact is offline";
-                        ui.add(
-                            egui::Image::new(if let Some(status) = &contact.status {
-                                if contact.lists.contains(&MsnpList::BlockList) {
-                                    alt_text = "Contact is blocked";
-                                    svg::default_display_picture_blocked()
-                                } else {
-                                    match status.status {
-                                        MsnpStatus::Busy | MsnpStatus::OnThePhone => {
-                                            alt_text = "Contact is busy";
-                                            svg::default_display_picture_busy()
-                                        }
+                        if !filter(contact) {
+                            continue;
+                        }
 
-                                        MsnpStatus::Away
-                                        | MsnpStatus::Idle
-                                        | MsnpStatus::BeRightBack
-                                        | MsnpStatus::OutToLunch => {
-                                            alt_text = "Contact is away";
-                                            svg::default_display_picture_away()
-                                        }
+                        let user_email = user_email.clone();
+                        let user_display_name = user_display_name.clone();
+                        let user_display_picture = user_display_picture.clone();
+                        let contact_repository = contact_repository.clone();
+                        let client = client.clone();
+
+                        ui.horizontal(|ui| {
+                            let mut alt_text = "Contact is offline";
+                            ui.add(
+                                egui::Image::new(if let Some(status) = &contact.status {
+                                    if contact.lists.contains(&MsnpList::BlockList) {
+                                        alt_text = "Contact is blocked";
+                                        svg::default_display_picture_blocked()
+                                    } else {
+                                        match status.status {
+                                            MsnpStatus::Busy | MsnpStatus::OnThePhone => {
+                                                alt_text = "Contact is busy";
+                                                svg::default_display_picture_busy()
+                                            }
+
+                                            MsnpStatus::Away
+                                            | MsnpStatus::Idle
+                                            | MsnpStatus::BeRightBack
+                                            | MsnpStatus::OutToLunch => {
+                                                alt_text = "Contact is away";
+                                                svg::default_display_picture_away()
+                                            }
 
-                                        _ => {
-                                            alt_text = "Contact is online";
-                                            svg::default_display_picture()
+                                            _ => {
+                                                alt_text = "Contact is online";
+                                                svg::default_display_picture()
+                                            }
                                         }
                                     }
-                                }
-                            } else if contact.lists.contains(&MsnpList::BlockList) {
-                                alt_text = "Contact is offline and blocked";
-                                svg::default_display_picture_offline_blocked()
-                            } else {
-                                svg::default_display_picture_offline()
-                            })
-                            .fit_to_exact_size(egui::Vec2::splat(25.))
-                            .alt_text(alt_text),
-                        );
-
-                        let mut contact_job = LayoutJob::default();
-                        contact_job.append(
-                            &contact.display_name,
-                            0.,
-                            TextFormat {
-                                font_id: FontId::proportional(14.),
-                                color: ui.visuals().text_color(),
-                                ..Default::default()
-                            },
-                        );
-
-                        if let Some(personal_message) = contact.personal_message.as_ref()
-                            && !personal_message.is_empty()
-                        {
-                            contact_job.append(
-                                " - ",
-                                0.,
-                                TextFormat {
-                                    font_id: FontId::proportional(14.),
-                                    color: ui.visuals().weak_text_color(),
-                                    ..Default::default()
-                                },
+                                } else if contact.lists.contains(&MsnpList::BlockList) {
+                                    alt_text = "Contact is offline and blocked";
+                                    svg::default_display_picture_offline_blocked()
+                                } else {
+                                    svg::default_display_picture_offline()
+                                })
+                                .fit_to_exact_size(egui::Vec2::splat(if compact {
+                                    16.
+                                } else {
+                                    25.
+                                }))
+                                .alt_text(alt_text),
                             );
 
+                            let alert = alerted_contacts.get(&contact.email);
+                            let last_seen_text = last_seen
+                                .get(&contact.email)
+                                .filter(|_| contact.status.is_none())
+                                .and_then(|timestamp| Local.timestamp_opt(*timestamp, 0).single())
+                                .map(|date_time| {
+                                    format!("Last seen {}\n", date_time.format("%B %-d, %Y %H:%M"))
+                                })
+                                .unwrap_or_default();
+
+                            let mut contact_job = LayoutJob::default();
                             contact_job.append(
-                                personal_message,
+                                contact.name(),
                                 0.,
                                 TextFormat {
                                     font_id: FontId::proportional(14.),
-                                    color: ui.visuals().weak_text_color(),
+                                    color: if alert.is_some() {
+                                        ui.visuals().warn_fg_color
+                                    } else {
+                                        ui.visuals().text_color()
+                                    },
                                     ..Default::default()
                                 },
                             );
-                        }
 
-                        ui.style_mut().spacing.button_padding = egui::Vec2::new(5., 3.);
-                        let label = ui
-                            .add(
-                                egui::Button::selectable(
-                                    selected_contact.as_ref().is_some_and(|selected_contact| {
-                                        *selected_contact == contact.email
-                                    }),
-                                    contact_job.clone(),
+                            if let Some(personal_message) = contact.personal_message.as_ref()
+                                && !personal_message.is_empty()
+                                && !compact
+                            {
+                                contact_job.append(
+                                    " - ",
+                                    0.,
+                                    TextFormat {
+                                        font_id: FontId::proportional(14.),
+                                        color: ui.visuals().weak_text_color(),
+                                        ..Default::default()
+                                    },
+                                );
+
+                                contact_job.append(
+                                    personal_message,
+                                    0.,
+                                    TextFormat {
+                                        font_id: FontId::proportional(14.),
+                                        color: ui.visuals().weak_text_color(),
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+
+                            ui.style_mut().spacing.button_padding =
+                                egui::Vec2::new(5., if compact { 1. } else { 3. });
+                            let label = ui
+                                .add(
+                                    egui::Button::selectable(
+                                        selected_contact.as_ref().is_some_and(|selected_contact| {
+                                            *selected_contact == contact.email
+                                        }),
+                                        contact_job.clone(),
+                                    )
+                                    .truncate(),
                                 )
-                                .truncate(),
-                            )
-                            .on_hover_text(format!(
-                                "{} ({})\n<{}>\nRight click for contact options.",
-                                contact_job.text,
-                                match contact.status.clone() {
-                                    Some(status) => match status.status {
-                                        MsnpStatus::Busy | MsnpStatus::OnThePhone => {
-                                            "Busy"
-                                        }
+                                .on_hover_text(format!(
+                                    "{} ({})\n{}<{}>\n{}{}Right click for contact options.",
+                                    contact_job.text,
+                                    match contact.status.clone() {
+                                        Some(status) => match status.status {
+                                            MsnpStatus::Busy | MsnpStatus::OnThePhone => {
+                                                "Busy"
+                                            }
 
-                                        MsnpStatus::Away
-                                        | MsnpStatus::BeRightBack
-                                        | MsnpStatus::OutToLunch => {
-                                            "Away"
-                                        }
+                                            MsnpStatus::Away
+                                            | MsnpStatus::BeRightBack
+                                            | MsnpStatus::OutToLunch => {
+                                                "Away"
+                                            }
 
-                                        MsnpStatus::Idle => "Idle",
-                                        _ => "Online",
+                                            MsnpStatus::Idle => "Idle",
+                                            _ => "Online",
+                                        },
+
+                                        None => "Offline",
                                     },
+                                    contact
+                                        .real_name()
+                                        .map(|real_name| format!("Real name: {real_name}\n"))
+                                        .unwrap_or_default(),
+                                    contact.email,
+                                    last_seen_text,
+                                    alert.map(|alert| format!("{alert}\n")).unwrap_or_default()
+                                ));
 
-                                    None => "Offline",
-                                },
-                                contact.email
-                            ));
+                            if *scroll_to_selected
+                                && selected_contact.as_ref().is_some_and(|selected_contact| {
+                                    *selected_contact == contact.email
+                                })
+                            {
+                                label.scroll_to_me(None);
+                                *scroll_to_selected = false;
+                            }
 
-                        if label.clicked() || label.secondary_clicked() {
-                            *selected_contact = Some(contact.email.clone());
-                        }
+                            if label.clicked() || label.secondary_clicked() {
+                                *selected_contact = Some(contact.email.clone());
+                                alerted_contacts.remove(&contact.email);
+                            }
 
-                        let msnp_user_status = match user_status {
-                            Status::Busy => MsnpStatus::Busy,
-                            Status::Away => MsnpStatus::Away,
-                            Status::AppearOffline => MsnpStatus::AppearOffline,
-                            _ => MsnpStatus::Online,
-                        };
+                            let msnp_user_status = match user_status {
+                                Status::Busy => MsnpStatus::Busy,
+                                Status::Away => MsnpStatus::Away,
+                                Status::AppearOffline => MsnpStatus::AppearOffline,
+                                _ => MsnpStatus::Online,
+                            };
 
-                        if label.double_clicked()
-                            && contact.status.is_some()
-                            && !contact.opening_conversation
-                            && user_status != Status::AppearOffline
-                        {
-                            contact.opening_conversation = true;
-                            let _ =
-                                main_window_sender.send(main_window::Message::OpenConversation {
-                                    user_email: user_email.clone(),
-                                    user_display_name: user_display_name.clone(),
-                                    user_display_picture: user_display_picture.clone(),
-                                    user_status: msnp_user_status.clone(),
-                                    contact_repository: contact_repository.clone(),
-                                    contact: contact.clone(),
-                                    client: client.clone(),
-                                });
-                        }
+                            if label.double_clicked()
+                                && contact.status.is_some()
+                                && !contact.opening_conversation
+                                && user_status != Status::AppearOffline
+                            {
+                                contact.opening_conversation = true;
+                                let _ = main_window_sender.send(
+                                    main_window::Message::OpenConversation {
+                                        user_email: user_email.clone(),
+                                        user_display_name: user_display_name.clone(),
+                                        user_display_picture: user_display_picture.clone(),
+                                        user_status: msnp_user_status.clone(),
+                                        contact_repository: contact_repository.clone(),
+                                        contact: contact.clone(),
+                                        client: client.clone(),
+                                        focused_message: None,
+                                    },
+                                );
+                            }
 
-                        ui.style_mut().spacing.button_padding = egui::Vec2::splat(5.);
-                        label.context_menu(|ui| {
-                            ui.with_layout(
+                            ui.style_mut().spacing.button_padding = egui::Vec2::splat(5.);
+                            label.context_menu(|ui| {
+                                ui.with_layout(
                                 egui::Layout::top_down_justified(egui::Align::LEFT),
                                 |ui| {
                                     if ui.button("Send an Instant Message").clicked()
@@ -199,10 +268,65 @@ pub fn category_collapsing_header(
                                                 contact_repository,
                                                 contact: contact.clone(),
                                                 client: client.clone(),
+                                                focused_message: None,
                                             },
                                         );
                                     }
 
+                                    if ui.button("Properties...").clicked() {
+                                        let _ = contacts_sender.send(
+                                            contacts::Message::OpenContactProperties(
+                                                contact.email.clone(),
+                                            ),
+                                        );
+                                    }
+
+                                    if ui
+                                        .add_enabled(
+                                            contact.guid.is_some(),
+                                            egui::Button::new("Rename..."),
+                                        )
+                                        .clicked()
+                                    {
+                                        let _ = contacts_sender.send(
+                                            contacts::Message::OpenRenameContact(contact.clone()),
+                                        );
+                                    }
+
+                                    if ui.button("Set Alias...").clicked() {
+                                        let _ = contacts_sender.send(
+                                            contacts::Message::OpenContactAlias(contact.clone()),
+                                        );
+                                    }
+
+                                    if ui.button("Alerts...").clicked() {
+                                        let _ = contacts_sender.send(
+                                            contacts::Message::OpenContactAlerts(contact.clone()),
+                                        );
+                                    }
+
+                                    if ui.button("Export History...").clicked() {
+                                        let _ =
+                                            contacts_sender.send(contacts::Message::ExportHistory(
+                                                Some(contact.email.clone()),
+                                            ));
+                                    }
+
+                                    if let Some(guid) = contact.guid.clone()
+                                        && groups.iter().any(|group| group.guid.is_some())
+                                    {
+                                        ui.separator();
+                                        group_menus(
+                                            ui,
+                                            contact,
+                                            guid,
+                                            groups,
+                                            contacts_sender.clone(),
+                                            handle.clone(),
+                                            client.clone(),
+                                        );
+                                    }
+
                                     ui.separator();
 
                                     if contact.lists.contains(&MsnpList::BlockList) {
@@ -263,17 +387,137 @@ pub fn category_collapsing_header(
                                     }
                                 },
                             );
+                            });
                         });
-                    });
+                    }
                 }
             }
         })
-        .header_response
-        .on_hover_text(format!("{} contacts", name))
-        .clicked()
+        .header_response;
+
+    let header = if group.is_some() {
+        header.on_hover_text(format!("{name} group\nRight click for group options."))
+    } else {
+        header
+    };
+
+    header.context_menu(|ui| {
+        ui.style_mut().spacing.button_padding = egui::Vec2::splat(5.);
+        ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+            if let Some(group) = group {
+                if ui.button("Create a Group...").clicked() {
+                    let _ = contacts_sender.send(contacts::Message::OpenGroupName(None));
+                }
+
+                let ready = group.guid.is_some();
+                if ui
+                    .add_enabled(ready, egui::Button::new("Rename Group..."))
+                    .on_disabled_hover_text(NEW_GROUP_HINT)
+                    .clicked()
+                {
+                    let _ =
+                        contacts_sender.send(contacts::Message::OpenGroupName(Some(group.clone())));
+                }
+
+                if ui
+                    .add_enabled(ready, egui::Button::new("Delete Group"))
+                    .on_disabled_hover_text(NEW_GROUP_HINT)
+                    .clicked()
+                    && let Some(guid) = group.guid.clone()
+                {
+                    let client = client.clone();
+                    let group_guid = guid.clone();
+                    run_future(
+                        handle.clone(),
+                        async move { client.delete_group(&group_guid).await },
+                        contacts_sender.clone(),
+                        move |result| contacts::Message::DeleteGroupResult(guid.clone(), result),
+                    );
+                }
+            }
+        });
+    });
+
+    if header.clicked()
         && let Some(contact) = selected_contact
-        && contacts.contains_key(contact)
+        && sections
+            .iter()
+            .any(|(contacts, _)| contacts.contains_key(contact))
     {
         *selected_contact = None;
     }
 }
+
+// Submenus to add the contact to the groups it isn't in, and to remove it from the ones it is
+fn group_menus(
+    ui: &mut Ui,
+    contact: &Contact,
+    contact_guid: Arc<String>,
+    groups: &[Group],
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+    client: Arc<Client>,
+) {
+    let groups = groups
+        .iter()
+        .filter_map(|group| group.guid.clone().map(|guid| (guid, group.name.clone())));
+
+    let (member_groups, other_groups): (Vec<_>, Vec<_>) =
+        groups.partition(|(guid, _)| contact.groups.contains(guid));
+
+    ui.add_enabled_ui(!other_groups.is_empty(), |ui| {
+        ui.menu_button("Add to Group", |ui| {
+            for (group_guid, name) in other_groups {
+                if ui.button(name.as_str()).clicked() {
+                    let client = client.clone();
+                    let contact_guid = contact_guid.clone();
+                    let email = contact.email.clone();
+                    let sent_group_guid = group_guid.clone();
+
+                    run_future(
+                        handle.clone(),
+                        async move {
+                            client
+                                .add_contact_to_group(&contact_guid, &sent_group_guid)
+                                .await
+                        },
+                        contacts_sender.clone(),
+                        move |result| contacts::Message::AddToGroupResult {
+                            email: email.clone(),
+                            group_guid: group_guid.clone(),
+                            result,
+                        },
+                    );
+                }
+            }
+        });
+    });
+
+    ui.add_enabled_ui(!member_groups.is_empty(), |ui| {
+        ui.menu_button("Remove from Group", |ui| {
+            for (group_guid, name) in member_groups {
+                if ui.button(name.as_str()).clicked() {
+                    let client = client.clone();
+                    let contact_guid = contact_guid.clone();
+                    let email = contact.email.clone();
+                    let sent_group_guid = group_guid.clone();
+
+                    run_future(
+                        handle.clone(),
+                        async move {
+                            client
+                                .remove_contact_from_group(&contact_guid, &sent_group_guid)
+                                .await
+                        },
+                        contacts_sender.clone(),
+                        move |result| contacts::Message::RemoveFromGroupResult {
+                            email: email.clone(),
+                            group_guid: group_guid.clone(),
+                            result,
+                        },
+                    );
+                }
+            }
+        });
+    });
+}
@@ -1,25 +1,42 @@
 use crate::contact_repository::ContactRepository;
+use crate::helpers::export_history::{export_history, pick_export_file};
 use crate::helpers::get_config::get_config;
+use crate::helpers::outbox;
+use crate::helpers::prune_history::prune_history_periodically;
 use crate::helpers::run_future::run_future;
+use crate::models::added_by_request::AddedByRequest;
+use crate::models::alert_rule::AlertRule;
 use crate::models::contact::Contact;
 use crate::models::display_picture::DisplayPicture;
+use crate::models::group::Group;
 use crate::models::sign_in_return::SignInReturn;
 use crate::models::switchboard_and_participants::SwitchboardAndParticipants;
 use crate::models::tab::Tab;
-use crate::screens::add_contact;
 use crate::screens::contacts::category_collapsing_header::category_collapsing_header;
 use crate::screens::contacts::status_selector::{Status, status_selector};
 use crate::screens::conversation::conversation;
+use crate::screens::{
+    add_contact, added_by, contact_alerts, contact_alias, contact_properties, group_name, history,
+    import_logs, rename_contact, search,
+};
+use crate::settings::{ContactGrouping, ContactListView, ContactSortOrder};
 use crate::sqlite::Sqlite;
 use crate::{main_window, models, screens, settings, svg};
 use eframe::egui;
 use eframe::egui::OpenUrl;
 use egui_taffy::taffy::prelude::{length, percent};
 use egui_taffy::{TuiBuilderLogic, taffy, tui};
-use msnp11_sdk::{Client, ContactError, MsnpList, MsnpStatus, PersonalMessage, SdkError};
-use std::collections::{BTreeMap, HashMap};
+use msnp11_sdk::{
+    Client, ContactError, MessagingError, MsnpList, MsnpStatus, PersonalMessage, SdkError,
+    Switchboard,
+};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::{Arc, mpsc};
 use tokio::runtime::Handle;
+use tokio_util::sync::CancellationToken;
+
+// Seconds after signing in by which the contact list has been handled
+const LIST_SYNC_TIMEOUT: i64 = 5;
 
 pub enum Message {
     DisplayPictureResult(anyhow::Result<DisplayPicture>),
@@ -33,8 +50,61 @@ pub enum Message {
     UnblockResult(Arc<String>, Result<(), ContactError>),
     DeleteResult(Arc<String>, Result<(), ContactError>),
     AddContactResult(Box<Result<msnp11_sdk::Event, ContactError>>),
+    OpenConversation(Arc<String>),
+    OpenContactProperties(Arc<String>),
+    CloseContactProperties,
+    OpenContactAlias(Contact),
+    CloseContactAlias,
+    SetAlias(Arc<String>, Option<Arc<String>>),
+    OpenContactAlerts(Contact),
+    CloseContactAlerts,
+    SetAlertRule(Arc<String>, Option<AlertRule>),
+    OpenRenameContact(Contact),
+    CloseRenameContact,
+    RenameContactResult {
+        email: Arc<String>,
+        name: Arc<String>,
+        result: Result<(), ContactError>,
+    },
+    OpenGroupName(Option<Group>),
+    CloseGroupName,
+    CreateGroupResult(Arc<String>, Result<(), ContactError>),
+    RenameGroupResult {
+        guid: Arc<String>,
+        name: Arc<String>,
+        result: Result<(), ContactError>,
+    },
+    DeleteGroupResult(Arc<String>, Result<(), ContactError>),
+    AddToGroupResult {
+        email: Arc<String>,
+        group_guid: Arc<String>,
+        result: Result<(), ContactError>,
+    },
+    RemoveFromGroupResult {
+        email: Arc<String>,
+        group_guid: Arc<String>,
+        result: Result<(), ContactError>,
+    },
     GetConfigResult(anyhow::Result<models::config::Config>),
     CloseAddContact,
+    OpenSearchResult {
+        email: Arc<String>,
+        message_id: i64,
+    },
+    CloseSearch,
+    CloseHistory,
+    CloseImportLogs,
+    ExportHistory(Option<Arc<String>>),
+    ExportHistoryResult(anyhow::Result<Option<usize>>),
+    AnswerAddedBy(Arc<String>, added_by::Answer),
+    AddedByResult {
+        request: AddedByRequest,
+        answer: added_by::Answer,
+        result: Box<Result<Option<msnp11_sdk::Event>, ContactError>>,
+    },
+    DismissAddedBy,
+    OutboxSessionResult(Arc<String>, Result<Arc<Switchboard>, SdkError>),
+    OutboxSendResult(i64, Result<(), MessagingError>),
 }
 
 pub struct Contacts {
@@ -48,7 +118,17 @@ pub struct Contacts {
     online_contacts: BTreeMap<Arc<String>, Contact>,
     offline_contacts: BTreeMap<Arc<String>, Contact>,
     contact_repository: ContactRepository,
+    unsynced_contacts: HashSet<Arc<String>>,
+    list_sync_deadline: i64,
+    groups: Vec<Group>,
+    contact_filter: String,
+    view: ContactListView,
+    last_activity: HashMap<Arc<String>, i64>,
+    alert_rules: HashMap<Arc<String>, AlertRule>,
+    alerted_contacts: HashMap<Arc<String>, String>,
+    last_seen: HashMap<Arc<String>, i64>,
     selected_contact: Option<Arc<String>>,
+    scroll_to_selected: bool,
     client: Arc<Client>,
     blp_bl: bool,
     sender: mpsc::Sender<Message>,
@@ -57,8 +137,24 @@ pub struct Contacts {
     tabs: Option<Vec<Tab>>,
     today_url: Option<String>,
     add_contact_window: Option<add_contact::AddContact>,
+    group_name_window: Option<group_name::GroupName>,
+    contact_alias_window: Option<contact_alias::ContactAlias>,
+    contact_alerts_window: Option<contact_alerts::ContactAlerts>,
+    contact_properties_window: Option<contact_properties::ContactProperties>,
+    rename_contact_window: Option<rename_contact::RenameContact>,
+    search_window: Option<search::Search>,
+    history_window: Option<history::History>,
+    import_logs_window: Option<import_logs::ImportLogs>,
+    added_by_window: Option<added_by::AddedBy>,
+    added_by_requests: VecDeque<AddedByRequest>,
+    added_by_dismissed: bool,
     orphan_switchboards: HashMap<Arc<String>, SwitchboardAndParticipants>,
+    next_outbox_check: i64,
+    forced_outbox: HashSet<Arc<String>>,
+    outbox_deliveries: HashMap<Arc<String>, i64>,
+    outbox_switchboards: HashMap<Arc<String>, Arc<String>>,
     handle: Handle,
+    prune_history_cancellation_token: CancellationToken,
 }
 
 impl Contacts {
@@ -84,6 +180,53 @@ impl Contacts {
             Message::GetConfigResult,
         );
 
+        let prune_history_cancellation_token = CancellationToken::new();
+        handle.spawn(prune_history_periodically(
+            sqlite.clone(),
+            sign_in_return.email.clone(),
+            prune_history_cancellation_token.clone(),
+        ));
+
+        let last_activity = sqlite
+            .select_last_activity(&sign_in_return.email)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(email, timestamp)| (Arc::new(email), timestamp))
+            .collect();
+
+        let last_seen = sqlite
+            .select_last_seen(&sign_in_return.email)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(email, timestamp)| (Arc::new(email), timestamp))
+            .collect();
+
+        let alert_rules = sqlite
+            .select_contact_alerts(&sign_in_return.email)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(email, rule)| (Arc::new(email), rule))
+            .collect();
+
+        // The cached list is shown until the server sends the current one
+        let contact_repository = ContactRepository::with_cache(
+            sqlite.clone(),
+            sign_in_return.email.clone(),
+            handle.clone(),
+        );
+
+        let cached_contacts = contact_repository.get_contacts().unwrap_or_default();
+        let unsynced_contacts = cached_contacts
+            .iter()
+            .map(|contact| contact.email.clone())
+            .collect();
+
+        let offline_contacts = cached_contacts
+            .into_iter()
+            .filter(|contact| contact.lists.contains(&MsnpList::ForwardList))
+            .map(|contact| (contact.email.clone(), contact))
+            .collect();
+
         Self {
             user_email: sign_in_return.email,
             display_name: Arc::new(String::from("")),
@@ -93,9 +236,19 @@ impl Contacts {
             selected_status,
             show_personal_message_frame: false,
             online_contacts: BTreeMap::new(),
-            offline_contacts: BTreeMap::new(),
-            contact_repository: ContactRepository::new(),
+            offline_contacts,
+            contact_repository,
+            unsynced_contacts,
+            list_sync_deadline: chrono::Local::now().timestamp() + LIST_SYNC_TIMEOUT,
+            groups: Vec::new(),
+            contact_filter: String::new(),
+            view: settings.contact_list_view,
+            last_activity,
+            alert_rules,
+            alerted_contacts: HashMap::new(),
+            last_seen,
             selected_contact: None,
+            scroll_to_selected: false,
             client: sign_in_return.client,
             blp_bl: false,
             sender,
@@ -104,8 +257,334 @@ impl Contacts {
             tabs: None,
             today_url: None,
             add_contact_window: None,
+            group_name_window: None,
+            contact_alias_window: None,
+            contact_alerts_window: None,
+            contact_properties_window: None,
+            rename_contact_window: None,
+            search_window: None,
+            history_window: None,
+            import_logs_window: None,
+            added_by_window: None,
+            added_by_requests: VecDeque::new(),
+            added_by_dismissed: false,
             orphan_switchboards: HashMap::new(),
+            next_outbox_check: 0,
+            forced_outbox: HashSet::new(),
+            outbox_deliveries: HashMap::new(),
+            outbox_switchboards: HashMap::new(),
             handle,
+            prune_history_cancellation_token,
+        }
+    }
+
+    // Runs once the server has sent the whole list, as cached contacts that weren't in it have been
+    // removed elsewhere
+    fn remove_unsynced_contacts(&mut self) {
+        for email in self.unsynced_contacts.drain() {
+            self.online_contacts.remove(&email);
+            self.offline_contacts.remove(&email);
+            self.contact_repository.remove_contact(&email);
+        }
+    }
+
+    // The contacts matching the filter, in the order the list shows them
+    fn listed_contacts(&self, filter: &str) -> Vec<Arc<String>> {
+        let mut emails = Vec::new();
+        let mut contacts: Vec<&Contact> = self
+            .sorted_emails(&self.online_contacts)
+            .into_iter()
+            .filter_map(|email| self.online_contacts.get(&email))
+            .collect();
+
+        if !self.view.hide_offline {
+            contacts.extend(
+                self.sorted_emails(&self.offline_contacts)
+                    .into_iter()
+                    .filter_map(|email| self.offline_contacts.get(&email)),
+            );
+        }
+
+        contacts.retain(|contact| matches_filter(contact, filter));
+        if !self.shows_groups() {
+            emails.extend(contacts.iter().map(|contact| contact.email.clone()));
+        } else {
+            for group in &self.groups {
+                emails.extend(
+                    contacts
+                        .iter()
+                        .filter(|contact| is_in_group(contact, group))
+                        .map(|contact| contact.email.clone()),
+                );
+            }
+
+            emails.extend(
+                contacts
+                    .iter()
+                    .filter(|contact| !self.groups.iter().any(|group| is_in_group(contact, group)))
+                    .map(|contact| contact.email.clone()),
+            );
+        }
+
+        // Contacts in more than one group are only visited once
+        let mut seen = HashSet::new();
+        emails.retain(|email| seen.insert(email.clone()));
+        emails
+    }
+
+    fn shows_groups(&self) -> bool {
+        self.view.grouping == ContactGrouping::Groups && !self.groups.is_empty()
+    }
+
+    fn sorted_emails(&self, contacts: &BTreeMap<Arc<String>, Contact>) -> Vec<Arc<String>> {
+        let mut sorted: Vec<&Contact> = contacts.values().collect();
+        sorted.sort_by_cached_key(|contact| contact.name().to_lowercase());
+
+        // Sorting is stable, so contacts that tie stay sorted by name
+        match self.view.sort_order {
+            ContactSortOrder::DisplayName => (),
+            ContactSortOrder::Status => sorted.sort_by_key(|contact| status_rank(contact)),
+            ContactSortOrder::RecentActivity => sorted.sort_by_key(|contact| {
+                std::cmp::Reverse(self.last_activity.get(&contact.email).copied())
+            }),
+        }
+
+        sorted
+            .into_iter()
+            .map(|contact| contact.email.clone())
+            .collect()
+    }
+
+    fn open_conversation(&mut self, email: &Arc<String>) {
+        let user_status = match self.selected_status {
+            Status::Busy => MsnpStatus::Busy,
+            Status::Away => MsnpStatus::Away,
+            Status::AppearOffline => MsnpStatus::AppearOffline,
+            _ => MsnpStatus::Online,
+        };
+
+        if let Some(contact) = self.online_contacts.get_mut(email)
+            && contact.status.is_some()
+            && !contact.opening_conversation
+            && user_status != MsnpStatus::AppearOffline
+        {
+            contact.opening_conversation = true;
+            let _ = self
+                .main_window_sender
+                .send(main_window::Message::OpenConversation {
+                    user_email: self.user_email.clone(),
+                    user_display_name: self.display_name.clone(),
+                    user_display_picture: self.display_picture.clone(),
+                    user_status,
+                    contact_repository: self.contact_repository.clone(),
+                    contact: contact.clone(),
+                    client: self.client.clone(),
+                    focused_message: None,
+                });
+        }
+    }
+
+    // Applies the contact's alert rule if it watches for this change, returning whether it showed a
+    // desktop notification
+    fn alert(
+        &mut self,
+        ui: &egui::Ui,
+        email: &Arc<String>,
+        text: String,
+        watches: impl Fn(&AlertRule) -> bool,
+    ) -> bool {
+        let Some(rule) = self
+            .alert_rules
+            .get(email)
+            .filter(|rule| watches(rule))
+            .copied()
+        else {
+            return false;
+        };
+
+        if rule.request_attention {
+            ui.send_viewport_cmd_to(
+                egui::ViewportId::ROOT,
+                egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Informational),
+            );
+        }
+
+        let notified = rule.notify && self.selected_status != Status::Busy;
+        if notified {
+            let _ = notify_rust::Notification::new()
+                .summary("Contact alert")
+                .body(&text)
+                .show();
+        }
+
+        if rule.highlight {
+            self.alerted_contacts.insert(email.clone(), text);
+        }
+
+        notified
+    }
+
+    fn queue_added_by(&mut self, request: AddedByRequest) {
+        if !self
+            .added_by_requests
+            .iter()
+            .any(|queued| queued.email == request.email)
+        {
+            self.added_by_requests.push_back(request);
+        }
+    }
+
+    // Delivers the outbox of online contacts no conversation is open with, as those send their own.
+    // Contacts that just signed in get everything that isn't already being sent.
+    pub fn send_outbox_messages(
+        &mut self,
+        ui: &egui::Ui,
+        conversations: &HashMap<egui::ViewportId, conversation::Conversation>,
+    ) {
+        let now = chrono::Local::now().timestamp();
+        if now < self.next_outbox_check {
+            return;
+        }
+
+        self.next_outbox_check = now + outbox::IDLE_CHECK;
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_secs(outbox::IDLE_CHECK as u64));
+
+        let forced_outbox = std::mem::take(&mut self.forced_outbox);
+
+        // Switchboards can't be opened while appearing offline
+        if self.selected_status == Status::AppearOffline {
+            return;
+        }
+
+        let Ok(outbox_contacts) = self
+            .sqlite
+            .select_outbox_contacts(&self.user_email, outbox::MAX_ATTEMPTS)
+        else {
+            return;
+        };
+
+        for contact in outbox_contacts {
+            let Some(email) = self
+                .online_contacts
+                .get(&contact)
+                .map(|contact| contact.email.clone())
+            else {
+                continue;
+            };
+
+            if conversations
+                .values()
+                .any(|conversation| conversation.outbox_contact().as_ref() == Some(&email))
+                || self
+                    .outbox_deliveries
+                    .get(&email)
+                    .is_some_and(|started| now - started < outbox::IDLE_CHECK)
+            {
+                continue;
+            }
+
+            let entries = self
+                .sqlite
+                .select_outbox(&self.user_email, &email)
+                .unwrap_or_default();
+
+            let (due_entries, _) =
+                outbox::due_entries(entries, forced_outbox.contains(&email), now);
+
+            if due_entries.is_empty() {
+                continue;
+            }
+
+            // Everything is sent once the contact joins the switchboard
+            self.outbox_deliveries.insert(email.clone(), now);
+            let client = self.client.clone();
+            run_future(
+                self.handle.clone(),
+                async move { client.create_session(&contact).await },
+                self.sender.clone(),
+                move |result| Message::OutboxSessionResult(email.clone(), result.map(Arc::from)),
+            );
+        }
+    }
+
+    fn deliver_outbox(&mut self, email: &Arc<String>, switchboard: Arc<Switchboard>) {
+        let now = chrono::Local::now().timestamp();
+        let entries = self
+            .sqlite
+            .select_outbox(&self.user_email, email)
+            .unwrap_or_default();
+
+        let (due_entries, _) = outbox::due_entries(entries, true, now);
+        for entry in due_entries {
+            let Some(id) = entry.message.id else {
+                continue;
+            };
+
+            outbox::start_attempt(&self.sqlite, &entry, now);
+            let switchboard = switchboard.clone();
+            run_future(
+                self.handle.clone(),
+                async move { outbox::send(&switchboard, &entry.message).await },
+                self.sender.clone(),
+                move |result| Message::OutboxSendResult(id, result),
+            );
+        }
+
+        self.outbox_deliveries.remove(email);
+    }
+
+    // Keeps a switchboard no conversation has claimed yet, so one can be opened once a message
+    // arrives on it
+    fn add_orphan_switchboard(
+        &mut self,
+        switchboard: Arc<Switchboard>,
+        ui: &egui::Ui,
+    ) -> Option<Arc<String>> {
+        let session_id = Arc::new(self.handle.block_on(switchboard.get_session_id()).ok()?);
+        self.orphan_switchboards.insert(
+            session_id.clone(),
+            SwitchboardAndParticipants {
+                switchboard: switchboard.clone(),
+                participants: Vec::new(),
+            },
+        );
+
+        let sender = self.main_window_sender.clone();
+        let handler_session_id = session_id.clone();
+        let ctx = ui.ctx().clone();
+
+        self.handle.block_on(async {
+            switchboard.add_event_handler_closure(move |event| {
+                let sender = sender.clone();
+                let session_id = handler_session_id.clone();
+                let ctx = ctx.clone();
+
+                async move {
+                    let _ = sender.send(main_window::Message::SwitchboardEvent(session_id, event));
+                    ctx.request_repaint();
+                }
+            });
+        });
+
+        Some(session_id)
+    }
+
+    fn edit_contact(&mut self, email: &Arc<String>, edit: impl Fn(&mut Contact)) {
+        let contact = if let Some(contact) = self.online_contacts.get_mut(email) {
+            Some(contact)
+        } else {
+            self.offline_contacts.get_mut(email)
+        };
+
+        if let Some(contact) = contact {
+            edit(contact);
+        }
+
+        if let Some(mut contact) = self.contact_repository.get_contact(email) {
+            edit(&mut contact);
+            self.contact_repository
+                .update_contacts(std::slice::from_ref(&contact));
         }
     }
 
@@ -115,12 +594,32 @@ impl Contacts {
         ui: &mut egui::Ui,
         conversations: &mut HashMap<egui::ViewportId, conversation::Conversation>,
     ) {
+        // The server sends the whole list at sign in before any other event
+        if let main_window::Message::NotificationServerEvent(event) = &message
+            && !is_list_event(event)
+        {
+            self.remove_unsynced_contacts();
+        }
+
         match message {
             main_window::Message::NotificationServerEvent(event) => match event {
                 msnp11_sdk::Event::DisplayName(display_name) => {
                     self.display_name = Arc::new(display_name);
                 }
 
+                msnp11_sdk::Event::Group { name, guid } => {
+                    let guid = Arc::new(guid);
+                    self.groups
+                        .retain(|group| group.guid.as_ref() != Some(&guid));
+
+                    self.groups.push(Group {
+                        guid: Some(guid),
+                        name: Arc::new(name),
+                    });
+
+                    self.groups.sort_by_key(|group| group.name.to_lowercase());
+                }
+
                 msnp11_sdk::Event::Contact {
                     email,
                     display_name,
@@ -133,16 +632,35 @@ impl Contacts {
                     };
 
                     let email = Arc::new(email);
+                    let cached_contact = self.contact_repository.get_contact(&email);
+                    self.unsynced_contacts.remove(&email);
+
+                    // People who added the user while they were offline
+                    if lists.contains(&MsnpList::PendingList) {
+                        self.queue_added_by(AddedByRequest {
+                            email: email.clone(),
+                            display_name: Arc::new(display_name.clone()),
+                        });
+                    }
+
                     let contact = Contact {
                         email: email.clone(),
                         display_name: Arc::new(display_name),
+                        alias: self.contact_repository.get_alias(&email),
                         guid: None,
                         lists,
+                        personal_message: cached_contact
+                            .as_ref()
+                            .and_then(|contact| contact.personal_message.clone()),
+                        display_picture: cached_contact.and_then(|contact| contact.display_picture),
                         ..Default::default()
                     };
 
                     self.contact_repository
                         .add_contacts(std::slice::from_ref(&contact));
+
+                    // Cached contacts might have been removed from the forward list elsewhere
+                    self.offline_contacts.remove(&email);
                 }
 
                 msnp11_sdk::Event::ContactInForwardList {
@@ -150,7 +668,7 @@ impl Contacts {
                     display_name,
                     guid,
                     lists,
-                    ..
+                    groups,
                 } => {
                     let display_name = if let Some(regex) = &*screens::PLUS_TAGS_REGEX {
                         regex.replace_all(&display_name, "").to_string()
@@ -159,11 +677,37 @@ impl Contacts {
                     };
 
                     let email = Arc::new(email);
+                    let cached_contact = self.contact_repository.get_contact(&email);
+                    self.unsynced_contacts.remove(&email);
+
+                    // People who added the user while they were offline
+                    if lists.contains(&MsnpList::PendingList) {
+                        self.queue_added_by(AddedByRequest {
+                            email: email.clone(),
+                            display_name: Arc::new(display_name.clone()),
+                        });
+                    }
+
+                    // The list carries the name the server keeps for the contact, which is the
+                    // latest rename if the user renamed them
+                    let display_name = Arc::new(display_name);
+                    let friendly_name = cached_contact
+                        .as_ref()
+                        .and_then(|contact| contact.friendly_name.as_ref())
+                        .map(|_| display_name.clone());
+
                     let contact = Contact {
                         email: email.clone(),
-                        display_name: Arc::new(display_name),
+                        display_name,
+                        alias: self.contact_repository.get_alias(&email),
+                        friendly_name,
                         guid: Some(Arc::new(guid)),
                         lists,
+                        groups: groups.into_iter().map(Arc::new).collect(),
+                        personal_message: cached_contact
+                            .as_ref()
+                            .and_then(|contact| contact.personal_message.clone()),
+                        display_picture: cached_contact.and_then(|contact| contact.display_picture),
                         ..Default::default()
                     };
 
@@ -215,6 +759,18 @@ impl Contacts {
                             previous_status = Some(presence.status.clone());
                         }
 
+                        if previous_status.as_ref() != Some(&presence.status) {
+                            let now = chrono::Local::now().timestamp();
+                            let _ = self.sqlite.insert_presence(
+                                &self.user_email,
+                                &email,
+                                Some(&presence.status),
+                                now,
+                            );
+
+                            self.last_seen.insert(contact.email.clone(), now);
+                        }
+
                         contact.display_name = Arc::new(display_name);
                         contact.status = Some(Arc::new(presence));
 
@@ -225,8 +781,9 @@ impl Contacts {
                     if let Some(contact) = contact.cloned()
                         && previous_status.is_none()
                     {
-                        self.contact_repository
-                            .update_contacts(std::slice::from_ref(&contact));
+                        // Messages waiting for them are sent right away
+                        self.forced_outbox.insert(contact.email.clone());
+                        self.next_outbox_check = 0;
 
                         self.offline_contacts.remove(&email);
                         self.online_contacts.insert(contact.email.clone(), contact);
@@ -271,6 +828,18 @@ impl Contacts {
                             previous_status = Some(presence.status.clone());
                         }
 
+                        if previous_status.as_ref() != Some(&presence.status) {
+                            let now = chrono::Local::now().timestamp();
+                            let _ = self.sqlite.insert_presence(
+                                &self.user_email,
+                                &email,
+                                Some(&presence.status),
+                                now,
+                            );
+
+                            self.last_seen.insert(contact.email.clone(), now);
+                        }
+
                         contact.display_name = Arc::new(display_name);
                         contact.status = Some(Arc::new(presence));
 
@@ -281,19 +850,37 @@ impl Contacts {
                     if let Some(contact) = contact.cloned()
                         && previous_status.is_none()
                     {
+                        // Messages waiting for them are sent right away
+                        self.forced_outbox.insert(contact.email.clone());
+                        self.next_outbox_check = 0;
+
+                        let text = format!("{} has just signed in", contact.name());
+                        let alerted =
+                            self.alert(ui, &contact.email, text.clone(), |rule| rule.on_sign_in);
+
                         let settings = settings::get_settings().unwrap_or_default();
-                        if settings.notify_sign_ins && self.selected_status != Status::Busy {
+                        if settings.notify_sign_ins
+                            && self.selected_status != Status::Busy
+                            && !alerted
+                        {
                             let _ = notify_rust::Notification::new()
                                 .summary("New sign in")
-                                .body(&format!("{} has just signed in", contact.display_name))
+                                .body(&text)
                                 .show();
                         }
 
-                        self.contact_repository
-                            .update_contacts(std::slice::from_ref(&contact));
-
                         self.offline_contacts.remove(&email);
                         self.online_contacts.insert(contact.email.clone(), contact);
+                    } else if let Some(previous_status) = previous_status
+                        && let Some(contact) = self.online_contacts.get(&email)
+                        && contact
+                            .status
+                            .as_ref()
+                            .is_some_and(|presence| presence.status != previous_status)
+                    {
+                        let text = format!("{} is now {}", contact.name(), contact.status_text());
+                        let email = contact.email.clone();
+                        self.alert(ui, &email, text, |rule| rule.on_status_change);
                     }
                 }
 
@@ -307,10 +894,36 @@ impl Contacts {
                         self.offline_contacts.get_mut(&email)
                     };
 
+                    let mut changed = None;
                     if let Some(contact) = contact {
+                        let personal_message_changed = contact
+                            .personal_message
+                            .as_ref()
+                            .is_some_and(|previous| **previous != personal_message.psm);
+
                         contact.personal_message = Some(Arc::new(personal_message.psm));
+                        contact.current_media = (!personal_message.current_media.is_empty())
+                            .then(|| Arc::new(personal_message.current_media));
                         self.contact_repository
                             .update_contacts(std::slice::from_ref(contact));
+
+                        // Only an actual change counts, not the message sent when they sign in
+                        if personal_message_changed {
+                            changed = Some(contact.clone());
+                        }
+                    }
+
+                    if let Some(contact) = changed {
+                        let text = match contact.personal_message.as_ref() {
+                            Some(personal_message) if !personal_message.is_empty() => format!(
+                                "{} changed their personal message to \"{personal_message}\"",
+                                contact.name()
+                            ),
+
+                            _ => format!("{} cleared their personal message", contact.name()),
+                        };
+
+                        self.alert(ui, &contact.email, text, |rule| rule.on_personal_message);
                     }
                 }
 
@@ -321,15 +934,25 @@ impl Contacts {
                         self.offline_contacts.get_mut(&email)
                     };
 
+                    let mut was_online = false;
                     if let Some(contact) = &mut contact {
+                        was_online = contact.status.is_some();
                         contact.status = None;
                         self.contact_repository
                             .update_contacts(std::slice::from_ref(contact));
                     }
 
                     if let Some(contact) = contact.cloned() {
-                        self.contact_repository
-                            .update_contacts(std::slice::from_ref(&contact));
+                        if was_online {
+                            let now = chrono::Local::now().timestamp();
+                            let _ =
+                                self.sqlite
+                                    .insert_presence(&self.user_email, &email, None, now);
+                            self.last_seen.insert(contact.email.clone(), now);
+
+                            let text = format!("{} has signed out", contact.name());
+                            self.alert(ui, &contact.email, text, |rule| rule.on_sign_out);
+                        }
 
                         self.online_contacts.remove(&email);
                         self.offline_contacts.insert(contact.email.clone(), contact);
@@ -337,35 +960,7 @@ impl Contacts {
                 }
 
                 msnp11_sdk::Event::SessionAnswered(switchboard) => {
-                    if let Ok(session_id) = self.handle.block_on(switchboard.get_session_id()) {
-                        let session_id = Arc::new(session_id);
-                        self.orphan_switchboards.insert(
-                            session_id.clone(),
-                            SwitchboardAndParticipants {
-                                switchboard: switchboard.clone(),
-                                participants: Vec::new(),
-                            },
-                        );
-
-                        let sender = self.main_window_sender.clone();
-                        let ui = ui.clone();
-
-                        self.handle.block_on(async {
-                            switchboard.add_event_handler_closure(move |event| {
-                                let sender = sender.clone();
-                                let session_id = session_id.clone();
-                                let ui = ui.clone();
-
-                                async move {
-                                    let _ = sender.send(main_window::Message::SwitchboardEvent(
-                                        session_id, event,
-                                    ));
-
-                                    ui.request_repaint();
-                                }
-                            });
-                        });
-                    }
+                    self.add_orphan_switchboard(switchboard, ui);
                 }
 
                 msnp11_sdk::Event::ServerMaintenanceScheduled { time_remaining } => {
@@ -380,15 +975,42 @@ impl Contacts {
 
                 msnp11_sdk::Event::AddedBy {
                     email,
-                    display_name: _,
+                    display_name,
                 } => {
+                    let display_name = if let Some(regex) = &*screens::PLUS_TAGS_REGEX {
+                        regex.replace_all(&display_name, "").to_string()
+                    } else {
+                        display_name
+                    };
+
+                    let request = AddedByRequest {
+                        email: Arc::new(email),
+                        display_name: Arc::new(display_name),
+                    };
+
+                    if let Some(mut contact) = self.contact_repository.get_contact(&request.email) {
+                        if !contact.lists.contains(&MsnpList::ReverseList) {
+                            contact.lists.push(MsnpList::ReverseList);
+                            self.contact_repository
+                                .update_contacts(std::slice::from_ref(&contact));
+                        }
+                    } else {
+                        self.contact_repository.add_contacts(&[Contact {
+                            email: request.email.clone(),
+                            display_name: request.display_name.clone(),
+                            alias: self.contact_repository.get_alias(&request.email),
+                            lists: vec![MsnpList::ReverseList],
+                            ..Default::default()
+                        }]);
+                    }
+
                     let settings = settings::get_settings().unwrap_or_default();
                     if settings.notify_added_by {
-                        let _ = notify_rust::Notification::new()
-                            .summary("Someone has added you")
-                            .body(&format!("{email} has added you to their contact list"))
-                            .show();
+                        added_by::notify(&request, self.sender.clone(), self.handle.clone());
                     }
+
+                    self.queue_added_by(request);
+                    ui.request_repaint();
                 }
 
                 _ => (),
@@ -398,6 +1020,11 @@ impl Contacts {
                 msnp11_sdk::Event::ParticipantInSwitchboard { email } => {
                     if let Some(switchboard) = self.orphan_switchboards.get_mut(&session_id) {
                         switchboard.participants.push(Arc::from(email));
+                        let switchboard = switchboard.switchboard.clone();
+
+                        if let Some(contact) = self.outbox_switchboards.remove(&session_id) {
+                            self.deliver_outbox(&contact, switchboard);
+                        }
                     }
                 }
 
@@ -409,7 +1036,11 @@ impl Contacts {
                     }
                 }
 
-                msnp11_sdk::Event::TextMessage { .. } | msnp11_sdk::Event::Nudge { .. } => {
+                msnp11_sdk::Event::TextMessage { ref email, .. }
+                | msnp11_sdk::Event::Nudge { ref email } => {
+                    self.last_activity
+                        .insert(Arc::new(email.clone()), chrono::Local::now().timestamp());
+
                     if let Some(switchboard) = self.orphan_switchboards.remove(&session_id) {
                         if let Some(conversation) =
                             conversations.values_mut().find(|conversation| {
@@ -496,6 +1127,10 @@ impl Contacts {
             }
 
             main_window::Message::ContactChatWindowFocused(email) => {
+                self.last_activity
+                    .insert(email.clone(), chrono::Local::now().timestamp());
+                self.alerted_contacts.remove(&email);
+
                 let contact = if let Some(contact) = self.online_contacts.get_mut(&email) {
                     Some(contact)
                 } else {
@@ -512,8 +1147,36 @@ impl Contacts {
     }
 }
 
+impl Drop for Contacts {
+    fn drop(&mut self) {
+        self.prune_history_cancellation_token.cancel();
+
+        // Nothing is known about who's online after signing out, so their timelines end here
+        let now = chrono::Local::now().timestamp();
+        for contact in self.online_contacts.values() {
+            let _ = self
+                .sqlite
+                .insert_presence(&self.user_email, &contact.email, None, now);
+        }
+    }
+}
+
 impl eframe::App for Contacts {
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        // Signing in only finishes once the whole list was received, so by now its events have
+        // been handled even if nothing else came after them
+        if !self.unsynced_contacts.is_empty() {
+            let now = chrono::Local::now().timestamp();
+            if now >= self.list_sync_deadline {
+                self.remove_unsynced_contacts();
+            } else {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs(
+                        (self.list_sync_deadline - now) as u64,
+                    ));
+            }
+        }
+
         if let Ok(message) = self.receiver.try_recv() {
             match message {
                 Message::DisplayPictureResult(result) => {
@@ -571,28 +1234,10 @@ impl eframe::App for Contacts {
                             .main_window_sender
                             .send(main_window::Message::OpenDialog(error.to_string()));
                     } else {
-                        let contact =
-                            if let Some(contact) = self.online_contacts.get_mut(&contact_email) {
-                                Some(contact)
-                            } else {
-                                self.offline_contacts.get_mut(&contact_email)
-                            };
-
-                        if let Some(contact) = contact {
-                            contact.lists.push(MsnpList::BlockList);
-                            contact.lists.retain(|list| list != &MsnpList::AllowList);
-
-                            self.contact_repository
-                                .update_contacts(std::slice::from_ref(contact));
-                        } else if let Some(mut contact) =
-                            self.contact_repository.get_contact(&contact_email)
-                        {
+                        self.edit_contact(&contact_email, |contact| {
                             contact.lists.push(MsnpList::BlockList);
                             contact.lists.retain(|list| list != &MsnpList::AllowList);
-
-                            self.contact_repository
-                                .update_contacts(std::slice::from_ref(&contact));
-                        }
+                        });
                     }
 
                     ui.request_repaint();
@@ -604,28 +1249,10 @@ impl eframe::App for Contacts {
                             .main_window_sender
                             .send(main_window::Message::OpenDialog(error.to_string()));
                     } else {
-                        let contact =
-                            if let Some(contact) = self.online_contacts.get_mut(&contact_email) {
-                                Some(contact)
-                            } else {
-                                self.offline_contacts.get_mut(&contact_email)
-                            };
-
-                        if let Some(contact) = contact {
-                            contact.lists.retain(|list| list != &MsnpList::BlockList);
-                            contact.lists.push(MsnpList::AllowList);
-
-                            self.contact_repository
-                                .update_contacts(std::slice::from_ref(contact));
-                        } else if let Some(mut contact) =
-                            self.contact_repository.get_contact(&contact_email)
-                        {
+                        self.edit_contact(&contact_email, |contact| {
                             contact.lists.retain(|list| list != &MsnpList::BlockList);
                             contact.lists.push(MsnpList::AllowList);
-
-                            self.contact_repository
-                                .update_contacts(std::slice::from_ref(&contact));
-                        }
+                        });
                     }
 
                     ui.request_repaint();
@@ -665,6 +1292,7 @@ impl eframe::App for Contacts {
                             let guid = Some(Arc::new(guid));
 
                             let contact = Contact {
+                                alias: self.contact_repository.get_alias(&email),
                                 email: email.clone(),
                                 display_name,
                                 guid,
@@ -688,34 +1316,532 @@ impl eframe::App for Contacts {
                     }
                 },
 
-                Message::GetConfigResult(result) => {
-                    if let Ok(config) = result {
-                        self.tabs = Some(config.tabs);
-                        self.today_url = Some(config.today_url);
+                Message::AnswerAddedBy(email, answer) => {
+                    // The same request can be answered from the dialog and the notification
+                    if let Some(index) = self
+                        .added_by_requests
+                        .iter()
+                        .position(|request| request.email == email)
+                        && let Some(request) = self.added_by_requests.remove(index)
+                    {
+                        self.added_by_window = None;
+                        let lists = self
+                            .contact_repository
+                            .get_contact(&email)
+                            .map(|contact| contact.lists)
+                            .unwrap_or_default();
+
+                        let allowed = lists.contains(&MsnpList::AllowList);
+                        let blocked = lists.contains(&MsnpList::BlockList);
+                        let pending = lists.contains(&MsnpList::PendingList);
+                        let display_name = request.display_name.clone();
+                        let client = self.client.clone();
+
+                        run_future(
+                            self.handle.clone(),
+                            async move {
+                                let mut event = None;
+                                if answer == added_by::Answer::AddToList {
+                                    event = Some(
+                                        client
+                                            .add_contact(
+                                                &email,
+                                                &display_name,
+                                                MsnpList::ForwardList,
+                                            )
+                                            .await?,
+                                    );
+                                }
+
+                                if answer == added_by::Answer::Block {
+                                    if !blocked {
+                                        client
+                                            .add_contact(&email, &email, MsnpList::BlockList)
+                                            .await?;
+                                    }
+
+                                    if allowed {
+                                        client.remove_contact(&email, MsnpList::AllowList).await?;
+                                    }
+                                } else {
+                                    if !allowed {
+                                        client
+                                            .add_contact(&email, &email, MsnpList::AllowList)
+                                            .await?;
+                                    }
+
+                                    if blocked {
+                                        client.remove_contact(&email, MsnpList::BlockList).await?;
+                                    }
+                                }
+
+                                if pending {
+                                    client.remove_contact(&email, MsnpList::PendingList).await?;
+                                }
+
+                                Ok(event)
+                            },
+                            self.sender.clone(),
+                            move |result| Message::AddedByResult {
+                                request: request.clone(),
+                                answer,
+                                result: Box::new(result),
+                            },
+                        );
                     }
+
+                    ui.request_repaint();
                 }
 
-                Message::CloseAddContact => self.add_contact_window = None,
-            }
-        }
+                Message::AddedByResult {
+                    request,
+                    answer,
+                    result,
+                } => {
+                    match *result {
+                        Ok(mut event) => {
+                            let mut lists = self
+                                .contact_repository
+                                .get_contact(&request.email)
+                                .map(|contact| contact.lists)
+                                .unwrap_or(vec![MsnpList::ReverseList]);
+
+                            let (added, removed) = match answer {
+                                added_by::Answer::Block => {
+                                    (MsnpList::BlockList, MsnpList::AllowList)
+                                }
 
-        egui::Panel::top("user_info")
-            .frame(egui::Frame {
-                inner_margin: egui::Margin {
-                    top: 15,
-                    bottom: 0,
-                    left: 15,
-                    right: 15,
-                },
-                fill: ui.visuals().window_fill,
-                ..Default::default()
-            })
-            .show_separator_line(false)
-            .show_inside(ui, |ui| {
-                tui(ui, ui.id().with("user_info"))
-                    .reserve_available_space()
-                    .style(taffy::Style {
-                        flex_direction: taffy::FlexDirection::Column,
+                                _ => (MsnpList::AllowList, MsnpList::BlockList),
+                            };
+
+                            lists.retain(|list| *list != MsnpList::PendingList && *list != removed);
+                            if !lists.contains(&added) {
+                                lists.push(added);
+                            }
+
+                            if let Some(msnp11_sdk::Event::ContactInForwardList {
+                                lists: forward_list_lists,
+                                ..
+                            }) = &mut event
+                            {
+                                lists.retain(|list| *list != MsnpList::ForwardList);
+                                lists.push(MsnpList::ForwardList);
+                                *forward_list_lists = lists;
+                            } else if self
+                                .contact_repository
+                                .get_contact(&request.email)
+                                .is_some()
+                            {
+                                self.edit_contact(&request.email, |contact| {
+                                    contact.lists = lists.clone()
+                                });
+                            } else {
+                                self.contact_repository.add_contacts(&[Contact {
+                                    email: request.email.clone(),
+                                    display_name: request.display_name.clone(),
+                                    alias: self.contact_repository.get_alias(&request.email),
+                                    lists,
+                                    ..Default::default()
+                                }]);
+                            }
+
+                            // Adding them to the list goes through the same path as Add a Contact
+                            if let Some(event) = event {
+                                let _ = self
+                                    .sender
+                                    .send(Message::AddContactResult(Box::new(Ok(event))));
+                            }
+                        }
+
+                        Err(error) => {
+                            // Kept so it can be answered again
+                            self.queue_added_by(request);
+                            let _ = self
+                                .main_window_sender
+                                .send(main_window::Message::OpenDialog(error.to_string()));
+                        }
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::DismissAddedBy => {
+                    self.added_by_window = None;
+                    self.added_by_dismissed = true;
+                }
+
+                Message::OutboxSessionResult(email, result) => {
+                    if let Ok(switchboard) = result
+                        && let Some(session_id) = self.add_orphan_switchboard(switchboard, ui)
+                    {
+                        self.outbox_switchboards.insert(session_id, email);
+                    } else {
+                        // Tried again on the next check
+                        self.outbox_deliveries.remove(&email);
+                    }
+                }
+
+                Message::OutboxSendResult(message_id, result) => {
+                    outbox::finish_attempt(&self.sqlite, message_id, result.is_ok());
+                }
+
+                Message::OpenConversation(email) => self.open_conversation(&email),
+                Message::OpenContactProperties(email) => {
+                    let contact = if let Some(contact) = self.online_contacts.get(&email) {
+                        Some(contact)
+                    } else {
+                        self.offline_contacts.get(&email)
+                    };
+
+                    if let Some(contact) = contact {
+                        if self.contact_properties_window.is_some() {
+                            ui.send_viewport_cmd_to(
+                                egui::ViewportId::from_hash_of("contact-properties"),
+                                egui::ViewportCommand::Focus,
+                            );
+                        }
+
+                        self.contact_properties_window =
+                            Some(contact_properties::ContactProperties::new(
+                                &self.user_email,
+                                contact,
+                                &self.sqlite,
+                                self.client.clone(),
+                                self.sender.clone(),
+                                self.handle.clone(),
+                            ));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::CloseContactProperties => self.contact_properties_window = None,
+                Message::OpenContactAlias(contact) => {
+                    if self.contact_alias_window.is_some() {
+                        ui.send_viewport_cmd_to(
+                            egui::ViewportId::from_hash_of("contact-alias"),
+                            egui::ViewportCommand::Focus,
+                        );
+                    } else {
+                        self.contact_alias_window = Some(contact_alias::ContactAlias::new(
+                            contact,
+                            self.sender.clone(),
+                        ));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::CloseContactAlias => self.contact_alias_window = None,
+                Message::SetAlias(email, alias) => {
+                    self.contact_repository
+                        .set_alias(email.clone(), alias.clone());
+
+                    self.edit_contact(&email, |contact| contact.alias = alias.clone());
+                    let _ = self
+                        .main_window_sender
+                        .send(main_window::Message::ContactNameChanged(email));
+
+                    ui.request_repaint();
+                }
+
+                Message::OpenRenameContact(contact) => {
+                    if self.rename_contact_window.is_some() {
+                        ui.send_viewport_cmd_to(
+                            egui::ViewportId::from_hash_of("rename-contact"),
+                            egui::ViewportCommand::Focus,
+                        );
+                    } else {
+                        self.rename_contact_window = Some(rename_contact::RenameContact::new(
+                            contact,
+                            self.client.clone(),
+                            self.sender.clone(),
+                            self.handle.clone(),
+                        ));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::CloseRenameContact => self.rename_contact_window = None,
+                Message::RenameContactResult {
+                    email,
+                    name,
+                    result,
+                } => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        self.edit_contact(&email, |contact| {
+                            contact.friendly_name = Some(name.clone())
+                        });
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::ContactNameChanged(email));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::OpenContactAlerts(contact) => {
+                    if self.contact_alerts_window.is_some() {
+                        ui.send_viewport_cmd_to(
+                            egui::ViewportId::from_hash_of("contact-alerts"),
+                            egui::ViewportCommand::Focus,
+                        );
+                    } else {
+                        let rule = self.alert_rules.get(&contact.email).copied();
+                        self.contact_alerts_window = Some(contact_alerts::ContactAlerts::new(
+                            contact,
+                            rule,
+                            self.sender.clone(),
+                        ));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::CloseContactAlerts => self.contact_alerts_window = None,
+                Message::SetAlertRule(email, rule) => {
+                    if let Err(error) =
+                        self.sqlite
+                            .set_contact_alert(&self.user_email, &email, rule.as_ref())
+                    {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else if let Some(rule) = rule {
+                        if !rule.highlight {
+                            self.alerted_contacts.remove(&email);
+                        }
+
+                        self.alert_rules.insert(email, rule);
+                    } else {
+                        self.alerted_contacts.remove(&email);
+                        self.alert_rules.remove(&email);
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::OpenGroupName(group) => {
+                    if self.group_name_window.is_some() {
+                        ui.send_viewport_cmd_to(
+                            egui::ViewportId::from_hash_of("group-name"),
+                            egui::ViewportCommand::Focus,
+                        );
+                    } else {
+                        self.group_name_window = Some(group_name::GroupName::new(
+                            group,
+                            self.client.clone(),
+                            self.sender.clone(),
+                            self.handle.clone(),
+                        ));
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::CloseGroupName => self.group_name_window = None,
+                Message::CreateGroupResult(name, result) => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        self.groups.push(Group { guid: None, name });
+                        self.groups.sort_by_key(|group| group.name.to_lowercase());
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::RenameGroupResult { guid, name, result } => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        for group in &mut self.groups {
+                            if group.guid.as_ref() == Some(&guid) {
+                                group.name = name.clone();
+                            }
+                        }
+
+                        self.groups.sort_by_key(|group| group.name.to_lowercase());
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::DeleteGroupResult(guid, result) => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        self.groups
+                            .retain(|group| group.guid.as_ref() != Some(&guid));
+
+                        let members: Vec<Arc<String>> = self
+                            .online_contacts
+                            .values()
+                            .chain(self.offline_contacts.values())
+                            .filter(|contact| contact.groups.contains(&guid))
+                            .map(|contact| contact.email.clone())
+                            .collect();
+
+                        for email in members {
+                            self.edit_contact(&email, |contact| {
+                                contact.groups.retain(|group| *group != guid)
+                            });
+                        }
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::AddToGroupResult {
+                    email,
+                    group_guid,
+                    result,
+                } => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        self.edit_contact(&email, |contact| {
+                            if !contact.groups.contains(&group_guid) {
+                                contact.groups.push(group_guid.clone());
+                            }
+                        });
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::RemoveFromGroupResult {
+                    email,
+                    group_guid,
+                    result,
+                } => {
+                    if let Err(error) = result {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(error.to_string()));
+                    } else {
+                        self.edit_contact(&email, |contact| {
+                            contact.groups.retain(|group| *group != group_guid)
+                        });
+                    }
+
+                    ui.request_repaint();
+                }
+
+                Message::GetConfigResult(result) => {
+                    if let Ok(config) = result {
+                        self.tabs = Some(config.tabs);
+                        self.today_url = Some(config.today_url);
+                    }
+                }
+
+                Message::CloseAddContact => self.add_contact_window = None,
+                Message::OpenSearchResult { email, message_id } => {
+                    let contact = self
+                        .contact_repository
+                        .get_contact(&email)
+                        .unwrap_or(Contact {
+                            email: email.clone(),
+                            display_name: email.clone(),
+                            ..Contact::default()
+                        });
+
+                    let user_status = match self.selected_status {
+                        Status::Busy => MsnpStatus::Busy,
+                        Status::Away => MsnpStatus::Away,
+                        Status::AppearOffline => MsnpStatus::AppearOffline,
+                        _ => MsnpStatus::Online,
+                    };
+
+                    let _ = self
+                        .main_window_sender
+                        .send(main_window::Message::OpenConversation {
+                            user_email: self.user_email.clone(),
+                            user_display_name: self.display_name.clone(),
+                            user_display_picture: self.display_picture.clone(),
+                            user_status,
+                            contact_repository: self.contact_repository.clone(),
+                            contact,
+                            client: self.client.clone(),
+                            focused_message: Some(message_id),
+                        });
+                }
+
+                Message::CloseSearch => self.search_window = None,
+                Message::CloseHistory => self.history_window = None,
+                Message::CloseImportLogs => self.import_logs_window = None,
+                Message::ExportHistory(contact) => {
+                    let file_name = match &contact {
+                        Some(contact) => format!("{contact} history.html"),
+                        None => "meowsn history.html".to_string(),
+                    };
+
+                    run_future(
+                        self.handle.clone(),
+                        export_history(
+                            pick_export_file(&file_name),
+                            self.sqlite.clone(),
+                            self.contact_repository.clone(),
+                            self.user_email.clone(),
+                            self.display_name.clone(),
+                            contact,
+                        ),
+                        self.sender.clone(),
+                        Message::ExportHistoryResult,
+                    );
+                }
+
+                Message::ExportHistoryResult(result) => {
+                    let text = match result {
+                        Ok(Some(message_count)) => {
+                            Some(format!("Exported {message_count} messages"))
+                        }
+
+                        Ok(None) => None,
+                        Err(error) => Some(format!("Could not export history: {error}")),
+                    };
+
+                    if let Some(text) = text {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(text));
+
+                        ui.request_repaint();
+                    }
+                }
+            }
+        }
+
+        egui::Panel::top("user_info")
+            .frame(egui::Frame {
+                inner_margin: egui::Margin {
+                    top: 15,
+                    bottom: 0,
+                    left: 15,
+                    right: 15,
+                },
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("user_info"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
                         size: percent(1.),
                         ..Default::default()
                     })
@@ -868,6 +1994,88 @@ impl eframe::App for Contacts {
                                             ));
                                     }
                                 }
+
+                                ui.add_space(10.);
+                                if ui
+                                    .link("Message History")
+                                    .on_hover_text("Browse your conversation history")
+                                    .clicked()
+                                {
+                                    if self.history_window.is_some() {
+                                        ui.send_viewport_cmd_to(
+                                            egui::ViewportId::from_hash_of("history"),
+                                            egui::ViewportCommand::Focus,
+                                        );
+                                    } else {
+                                        self.history_window = Some(history::History::new(
+                                            self.user_email.clone(),
+                                            self.display_name.clone(),
+                                            self.contact_repository.clone(),
+                                            self.sqlite.clone(),
+                                            self.sender.clone(),
+                                            self.handle.clone(),
+                                            ui.ctx().clone(),
+                                        ));
+                                    }
+                                }
+
+                                ui.add_space(10.);
+                                if ui
+                                    .link("Import Logs")
+                                    .on_hover_text("Import message logs from MSN Messenger and Windows Live Messenger")
+                                    .clicked()
+                                {
+                                    if self.import_logs_window.is_some() {
+                                        ui.send_viewport_cmd_to(
+                                            egui::ViewportId::from_hash_of("import-logs"),
+                                            egui::ViewportCommand::Focus,
+                                        );
+                                    } else {
+                                        self.import_logs_window = Some(import_logs::ImportLogs::new(
+                                            self.user_email.clone(),
+                                            self.display_name.clone(),
+                                            self.contact_repository.clone(),
+                                            self.sqlite.clone(),
+                                            self.sender.clone(),
+                                            self.handle.clone(),
+                                        ));
+                                    }
+                                }
+
+                                ui.add_space(10.);
+                                if ui
+                                    .link("Search History")
+                                    .on_hover_text("Search your conversation history")
+                                    .clicked()
+                                {
+                                    if self.search_window.is_some() {
+                                        ui.send_viewport_cmd_to(
+                                            egui::ViewportId::from_hash_of("search"),
+                                            egui::ViewportCommand::Focus,
+                                        );
+                                    } else {
+                                        self.search_window = Some(search::Search::new(
+                                            self.user_email.clone(),
+                                            self.contact_repository.clone(),
+                                            self.sqlite.clone(),
+                                            self.sender.clone(),
+                                        ));
+                                    }
+                                }
+
+                                if self.added_by_dismissed && !self.added_by_requests.is_empty() {
+                                    ui.add_space(10.);
+                                    if ui
+                                        .link(format!(
+                                            "Contact Requests ({})",
+                                            self.added_by_requests.len()
+                                        ))
+                                        .on_hover_text("Answer the people who added you")
+                                        .clicked()
+                                    {
+                                        self.added_by_dismissed = false;
+                                    }
+                                }
                             });
                         });
                     });
@@ -933,40 +2141,214 @@ impl eframe::App for Contacts {
                 ..Default::default()
             })
             .show_inside(ui, |ui| {
+                let filter_edit = ui
+                    .with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.menu_button("View", |ui| {
+                            if view_options(ui, &mut self.view) {
+                                let mut settings = settings::get_settings().unwrap_or_default();
+                                settings.contact_list_view = self.view;
+                                let _ = settings::save_settings(&settings);
+                            }
+
+                            // Also here so a first group can be made when there are no group headers
+                            ui.separator();
+                            if ui.button("Create a Group...").clicked() {
+                                let _ = self.sender.send(Message::OpenGroupName(None));
+                            }
+                        })
+                        .response
+                        .on_hover_text("Change how your contacts are listed or create a group");
+
+                        ui.add(
+                            egui::text_edit::TextEdit::singleline(&mut self.contact_filter)
+                                .hint_text("Find a contact...")
+                                .desired_width(f32::INFINITY),
+                        )
+                        .on_hover_text(
+                            "Filter your contacts by name, e-mail or personal message. Use the \
+                            arrow keys to pick one and Enter to send them a message.",
+                        )
+                    })
+                    .inner;
+
+                let filter = self.contact_filter.trim().to_lowercase();
+                if filter_edit.changed() {
+                    self.selected_contact = if filter.is_empty() {
+                        None
+                    } else {
+                        self.listed_contacts(&filter).first().cloned()
+                    };
+
+                    self.scroll_to_selected = self.selected_contact.is_some();
+                }
+
+                if filter_edit.has_focus() {
+                    let (up, down) = ui.input(|i| {
+                        (
+                            i.key_pressed(egui::Key::ArrowUp),
+                            i.key_pressed(egui::Key::ArrowDown),
+                        )
+                    });
+
+                    let listed_contacts = self.listed_contacts(&filter);
+                    if (up || down) && !listed_contacts.is_empty() {
+                        let index = self.selected_contact.as_ref().and_then(|selected| {
+                            listed_contacts.iter().position(|email| email == selected)
+                        });
+
+                        let index = match index {
+                            Some(index) if down => (index + 1).min(listed_contacts.len() - 1),
+                            Some(index) => index.saturating_sub(1),
+                            None => 0,
+                        };
+
+                        self.selected_contact = Some(listed_contacts[index].clone());
+                        self.scroll_to_selected = true;
+                    }
+                }
+
+                if filter_edit.lost_focus() {
+                    if ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && let Some(email) = self.selected_contact.clone()
+                    {
+                        self.open_conversation(&email);
+                    } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.contact_filter.clear();
+                    }
+                }
+
+                let online_order = self.sorted_emails(&self.online_contacts);
+                let offline_order = self.sorted_emails(&self.offline_contacts);
+
+                ui.add_space(5.);
                 egui::ScrollArea::vertical()
                     .auto_shrink(false)
                     .show(ui, |ui| {
-                        category_collapsing_header(
-                            ui,
-                            "Online",
-                            &mut self.selected_contact,
-                            &mut self.online_contacts,
-                            self.main_window_sender.clone(),
-                            self.sender.clone(),
-                            self.handle.clone(),
-                            self.user_email.clone(),
-                            self.display_name.clone(),
-                            self.display_picture.clone(),
-                            self.selected_status,
-                            self.contact_repository.clone(),
-                            self.client.clone(),
-                        );
+                        if !self.shows_groups() {
+                            category_collapsing_header(
+                                ui,
+                                "Online",
+                                None,
+                                &self.groups,
+                                |contact| matches_filter(contact, &filter),
+                                self.view.compact,
+                                &mut self.selected_contact,
+                                &mut self.scroll_to_selected,
+                                &mut self.alerted_contacts,
+                                &self.last_seen,
+                                &mut [(&mut self.online_contacts, &online_order)],
+                                self.main_window_sender.clone(),
+                                self.sender.clone(),
+                                self.handle.clone(),
+                                self.user_email.clone(),
+                                self.display_name.clone(),
+                                self.display_picture.clone(),
+                                self.selected_status,
+                                self.contact_repository.clone(),
+                                self.client.clone(),
+                            );
 
-                        category_collapsing_header(
-                            ui,
-                            "Offline",
-                            &mut self.selected_contact,
-                            &mut self.offline_contacts,
-                            self.main_window_sender.clone(),
-                            self.sender.clone(),
-                            self.handle.clone(),
-                            self.user_email.clone(),
-                            self.display_name.clone(),
-                            self.display_picture.clone(),
-                            self.selected_status,
-                            self.contact_repository.clone(),
-                            self.client.clone(),
-                        );
+                            if !self.view.hide_offline {
+                                category_collapsing_header(
+                                    ui,
+                                    "Offline",
+                                    None,
+                                    &self.groups,
+                                    |contact| matches_filter(contact, &filter),
+                                    self.view.compact,
+                                    &mut self.selected_contact,
+                                    &mut self.scroll_to_selected,
+                                    &mut self.alerted_contacts,
+                                    &self.last_seen,
+                                    &mut [(&mut self.offline_contacts, &offline_order)],
+                                    self.main_window_sender.clone(),
+                                    self.sender.clone(),
+                                    self.handle.clone(),
+                                    self.user_email.clone(),
+                                    self.display_name.clone(),
+                                    self.display_picture.clone(),
+                                    self.selected_status,
+                                    self.contact_repository.clone(),
+                                    self.client.clone(),
+                                );
+                            }
+                        } else {
+                            // Groups list their offline members after the online ones, and Other
+                            // Contacts does the same for everyone outside of a group
+                            for group in &self.groups {
+                                let in_category = |contact: &Contact| {
+                                    is_in_group(contact, group) && matches_filter(contact, &filter)
+                                };
+
+                                if self.view.hide_empty_groups
+                                    && !self.online_contacts.values().any(in_category)
+                                    && (self.view.hide_offline
+                                        || !self.offline_contacts.values().any(in_category))
+                                {
+                                    continue;
+                                }
+
+                                let mut sections =
+                                    vec![(&mut self.online_contacts, &online_order[..])];
+                                if !self.view.hide_offline {
+                                    sections.push((&mut self.offline_contacts, &offline_order[..]));
+                                }
+
+                                category_collapsing_header(
+                                    ui,
+                                    &group.name,
+                                    Some(group),
+                                    &self.groups,
+                                    in_category,
+                                    self.view.compact,
+                                    &mut self.selected_contact,
+                                    &mut self.scroll_to_selected,
+                                    &mut self.alerted_contacts,
+                                    &self.last_seen,
+                                    &mut sections,
+                                    self.main_window_sender.clone(),
+                                    self.sender.clone(),
+                                    self.handle.clone(),
+                                    self.user_email.clone(),
+                                    self.display_name.clone(),
+                                    self.display_picture.clone(),
+                                    self.selected_status,
+                                    self.contact_repository.clone(),
+                                    self.client.clone(),
+                                );
+                            }
+
+                            let mut sections = vec![(&mut self.online_contacts, &online_order[..])];
+                            if !self.view.hide_offline {
+                                sections.push((&mut self.offline_contacts, &offline_order[..]));
+                            }
+
+                            category_collapsing_header(
+                                ui,
+                                "Other Contacts",
+                                None,
+                                &self.groups,
+                                |contact| {
+                                    !self.groups.iter().any(|group| is_in_group(contact, group))
+                                        && matches_filter(contact, &filter)
+                                },
+                                self.view.compact,
+                                &mut self.selected_contact,
+                                &mut self.scroll_to_selected,
+                                &mut self.alerted_contacts,
+                                &self.last_seen,
+                                &mut sections,
+                                self.main_window_sender.clone(),
+                                self.sender.clone(),
+                                self.handle.clone(),
+                                self.user_email.clone(),
+                                self.display_name.clone(),
+                                self.display_picture.clone(),
+                                self.selected_status,
+                                self.contact_repository.clone(),
+                                self.client.clone(),
+                            );
+                        }
                     });
             });
 
@@ -985,6 +2367,158 @@ impl eframe::App for Contacts {
             );
         }
 
+        if let Some(group_name) = &mut self.group_name_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("group-name"),
+                egui::ViewportBuilder::default()
+                    .with_title(if group_name.is_renaming() {
+                        "Rename group"
+                    } else {
+                        "Create group"
+                    })
+                    .with_inner_size([400., 150.])
+                    .with_maximize_button(false)
+                    .with_minimize_button(false)
+                    .with_resizable(false),
+                |ui, _| {
+                    group_name.group_name(ui);
+                },
+            );
+        }
+
+        if !self.added_by_dismissed
+            && self.added_by_window.is_none()
+            && let Some(request) = self.added_by_requests.front()
+        {
+            self.added_by_window =
+                Some(added_by::AddedBy::new(request.clone(), self.sender.clone()));
+        }
+
+        if let Some(added_by) = &mut self.added_by_window {
+            let pending = self.added_by_requests.len();
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("added-by"),
+                egui::ViewportBuilder::default()
+                    .with_title("Contact request")
+                    .with_inner_size([450., 200.])
+                    .with_maximize_button(false)
+                    .with_minimize_button(false)
+                    .with_resizable(false),
+                |ui, _| {
+                    added_by.added_by(ui, pending);
+                },
+            );
+        }
+
+        if let Some(rename_contact) = &mut self.rename_contact_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("rename-contact"),
+                egui::ViewportBuilder::default()
+                    .with_title("Rename contact")
+                    .with_inner_size([400., 150.])
+                    .with_maximize_button(false)
+                    .with_minimize_button(false)
+                    .with_resizable(false),
+                |ui, _| {
+                    rename_contact.rename_contact(ui);
+                },
+            );
+        }
+
+        if let Some(contact_alerts) = &mut self.contact_alerts_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("contact-alerts"),
+                egui::ViewportBuilder::default()
+                    .with_title("Contact alerts")
+                    .with_inner_size([400., 330.])
+                    .with_maximize_button(false)
+                    .with_minimize_button(false)
+                    .with_resizable(false),
+                |ui, _| {
+                    contact_alerts.contact_alerts(ui);
+                },
+            );
+        }
+
+        if let Some(contact_alias) = &mut self.contact_alias_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("contact-alias"),
+                egui::ViewportBuilder::default()
+                    .with_title("Set alias")
+                    .with_inner_size([400., 150.])
+                    .with_maximize_button(false)
+                    .with_minimize_button(false)
+                    .with_resizable(false),
+                |ui, _| {
+                    contact_alias.contact_alias(ui);
+                },
+            );
+        }
+
+        if let Some(contact_properties) = &mut self.contact_properties_window {
+            let email = contact_properties.email().clone();
+            let contact = if let Some(contact) = self.online_contacts.get(&email) {
+                Some(contact)
+            } else {
+                self.offline_contacts.get(&email)
+            };
+
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("contact-properties"),
+                egui::ViewportBuilder::default()
+                    .with_title(format!("{email} - Properties"))
+                    .with_inner_size([520., 620.])
+                    .with_min_inner_size([400., 350.]),
+                |ui, _| {
+                    contact_properties.contact_properties(
+                        ui,
+                        contact,
+                        &self.groups,
+                        self.last_seen.get(&email).copied(),
+                    );
+                },
+            );
+        }
+
+        if let Some(search) = &mut self.search_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("search"),
+                egui::ViewportBuilder::default()
+                    .with_title("Search History")
+                    .with_inner_size([650., 500.])
+                    .with_min_inner_size([500., 350.]),
+                |ui, _| {
+                    search.search(ui);
+                },
+            );
+        }
+
+        if let Some(history) = &mut self.history_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("history"),
+                egui::ViewportBuilder::default()
+                    .with_title("Message History")
+                    .with_inner_size([700., 550.])
+                    .with_min_inner_size([500., 350.]),
+                |ui, _| {
+                    history.history(ui);
+                },
+            );
+        }
+
+        if let Some(import_logs) = &mut self.import_logs_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("import-logs"),
+                egui::ViewportBuilder::default()
+                    .with_title("Import Message Logs")
+                    .with_inner_size([500., 450.])
+                    .with_min_inner_size([400., 300.]),
+                |ui, _| {
+                    import_logs.import_logs(ui);
+                },
+            );
+        }
+
         if ui.input(|input| input.viewport().close_requested()) {
             let _ = self
                 .handle
@@ -992,3 +2526,87 @@ impl eframe::App for Contacts {
         }
     }
 }
+
+fn is_list_event(event: &msnp11_sdk::Event) -> bool {
+    matches!(
+        event,
+        msnp11_sdk::Event::Gtc(_)
+            | msnp11_sdk::Event::Blp(_)
+            | msnp11_sdk::Event::DisplayName(_)
+            | msnp11_sdk::Event::Group { .. }
+            | msnp11_sdk::Event::Contact { .. }
+            | msnp11_sdk::Event::ContactInForwardList { .. }
+    )
+}
+
+fn is_in_group(contact: &Contact, group: &Group) -> bool {
+    group
+        .guid
+        .as_ref()
+        .is_some_and(|guid| contact.groups.contains(guid))
+}
+
+// Takes the filter already lowercased
+fn matches_filter(contact: &Contact, filter: &str) -> bool {
+    filter.is_empty()
+        || contact.display_name.to_lowercase().contains(filter)
+        || contact
+            .friendly_name
+            .as_ref()
+            .is_some_and(|friendly_name| friendly_name.to_lowercase().contains(filter))
+        || contact
+            .alias
+            .as_ref()
+            .is_some_and(|alias| alias.to_lowercase().contains(filter))
+        || contact.email.to_lowercase().contains(filter)
+        || contact
+            .personal_message
+            .as_ref()
+            .is_some_and(|personal_message| personal_message.to_lowercase().contains(filter))
+}
+
+// Online contacts first, then busy, away and offline ones
+fn status_rank(contact: &Contact) -> u8 {
+    match contact.status.as_ref().map(|presence| &presence.status) {
+        Some(MsnpStatus::Busy | MsnpStatus::OnThePhone) => 1,
+        Some(
+            MsnpStatus::Away | MsnpStatus::Idle | MsnpStatus::BeRightBack | MsnpStatus::OutToLunch,
+        ) => 2,
+        Some(_) => 0,
+        None => 3,
+    }
+}
+
+// Returns whether any option changed
+fn view_options(ui: &mut egui::Ui, view: &mut ContactListView) -> bool {
+    let previous_view = *view;
+
+    ui.label("Sort contacts by");
+    ui.radio_value(
+        &mut view.sort_order,
+        ContactSortOrder::DisplayName,
+        "Display name",
+    );
+
+    ui.radio_value(&mut view.sort_order, ContactSortOrder::Status, "Status");
+    ui.radio_value(
+        &mut view.sort_order,
+        ContactSortOrder::RecentActivity,
+        "Recent activity",
+    );
+
+    ui.separator();
+    ui.label("Arrange contacts by");
+    ui.radio_value(&mut view.grouping, ContactGrouping::Groups, "Groups");
+    ui.radio_value(&mut view.grouping, ContactGrouping::Status, "Online status");
+
+    ui.separator();
+    ui.checkbox(&mut view.hide_offline, "Hide offline contacts");
+    ui.add_enabled(
+        view.grouping == ContactGrouping::Groups,
+        egui::Checkbox::new(&mut view.hide_empty_groups, "Hide empty groups"),
+    );
+
+    ui.checkbox(&mut view.compact, "Compact list");
+    *view != previous_view
+}
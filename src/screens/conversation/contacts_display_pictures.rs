@@ -23,25 +23,25 @@ pub fn contacts_display_pictures(
                         egui::Image::from_bytes(format!("bytes://{}", picture.hash), picture.data)
                             .fit_to_exact_size(egui::Vec2::splat(90.))
                             .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
-                            .alt_text(format!("Display picture for {}", participant.display_name))
+                            .alt_text(format!("Display picture for {}", participant.name()))
                     } else {
                         egui::Image::new(svg::default_display_picture())
                             .fit_to_exact_size(egui::Vec2::splat(90.))
-                            .alt_text(format!("Display picture for {}", participant.display_name))
+                            .alt_text(format!("Display picture for {}", participant.name()))
                     })
-                    .on_hover_text(format!("Display picture for {}", participant.display_name))
+                    .on_hover_text(format!("Display picture for {}", participant.name()))
                 } else if let Some(participant) = &last_participant {
                     ui.add(if let Some(picture) = participant.display_picture.clone() {
                         egui::Image::from_bytes(format!("bytes://{}", picture.hash), picture.data)
                             .fit_to_exact_size(egui::Vec2::splat(90.))
                             .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
-                            .alt_text(format!("Display picture for {}", participant.display_name))
+                            .alt_text(format!("Display picture for {}", participant.name()))
                     } else {
                         egui::Image::new(svg::default_display_picture())
                             .fit_to_exact_size(egui::Vec2::splat(90.))
-                            .alt_text(format!("Display picture for {}", participant.display_name))
+                            .alt_text(format!("Display picture for {}", participant.name()))
                     })
-                    .on_hover_text(format!("Display picture for {}", participant.display_name))
+                    .on_hover_text(format!("Display picture for {}", participant.name()))
                 } else {
                     ui.add(
                         egui::Image::new(svg::default_display_picture())
@@ -78,16 +78,13 @@ pub fn contacts_display_pictures(
                             )
                             .fit_to_exact_size(egui::Vec2::splat(44.))
                             .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
-                            .alt_text(format!("Display picture for {}", participant.display_name))
+                            .alt_text(format!("Display picture for {}", participant.name()))
                         } else {
                             egui::Image::new(svg::default_display_picture())
                                 .fit_to_exact_size(egui::Vec2::splat(44.))
-                                .alt_text(format!(
-                                    "Display picture for {}",
-                                    participant.display_name
-                                ))
+                                .alt_text(format!("Display picture for {}", participant.name()))
                         })
-                        .on_hover_text(format!("Display picture for {}", participant.display_name))
+                        .on_hover_text(format!("Display picture for {}", participant.name()))
                     });
                 });
 
@@ -95,7 +92,7 @@ pub fn contacts_display_pictures(
                     grid_column: line(2),
                     ..Default::default()
                 })
-                .ui_add(egui::Label::new(participant.display_name.as_str()).truncate());
+                .ui_add(egui::Label::new(participant.name().as_str()).truncate());
             }
         })
     }
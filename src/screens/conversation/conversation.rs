@@ -1,9 +1,11 @@
 use crate::contact_repository::ContactRepository;
+use crate::helpers::outbox;
 use crate::helpers::run_future::run_future;
 use crate::models::contact::Contact;
 use crate::models::display_picture::DisplayPicture;
 use crate::models::message;
 use crate::models::switchboard_and_participants::SwitchboardAndParticipants;
+use crate::models::transcript::{TranscriptKind, participants_key};
 use crate::screens::conversation::contacts_display_pictures::contacts_display_pictures;
 use crate::screens::conversation::messages::messages;
 use crate::screens::conversation::new_message_editor::new_message_editor;
@@ -16,11 +18,12 @@ use eframe::egui::{FontId, TextFormat};
 use egui_taffy::taffy::prelude::{fr, length, line, percent};
 use egui_taffy::{TuiBuilderLogic, taffy, tui};
 use msnp11_sdk::{Client, MessagingError, MsnpStatus, SdkError, Switchboard};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, mpsc};
 use tokio::runtime::Handle;
 
 const INITIAL_HISTORY_LIMIT: u32 = 3;
+const HISTORY_PAGE_SIZE: u32 = 25;
 
 pub enum Message {
     SendMessageResult(message::Message, Result<(), MessagingError>),
@@ -53,10 +56,25 @@ pub struct Conversation {
     italic: bool,
     underline: bool,
     strikethrough: bool,
+    color: [u8; 3],
     focused: bool,
     handle: Handle,
     viewport_id: egui::viewport::ViewportId,
     invite_window: Option<invite::Invite>,
+    focused_message: Option<i64>,
+    scroll_to_focused_message: bool,
+    history_start_reached: bool,
+    history_end_reached: bool,
+    previous_content_height: Option<f32>,
+    // Where the last page of older messages was loaded from, so it's only queried once
+    older_messages_cursor: Option<(i64, i64)>,
+    newer_messages_cursor: Option<(i64, i64)>,
+    next_outbox_check: i64,
+    queued_messages: HashSet<i64>,
+    // Group messages typed before anyone joined, sent to whoever joins this session first
+    queued_group_messages: Vec<message::Message>,
+    resend_message: Option<i64>,
+    confirm_clear_history: bool,
 }
 
 impl Conversation {
@@ -73,14 +91,22 @@ impl Conversation {
         sqlite: Sqlite,
         handle: Handle,
         viewport_id: egui::viewport::ViewportId,
+        focused_message: Option<i64>,
     ) -> Self {
-        let messages = if let Ok(mut message_history) =
-            sqlite.select_messages(&user_email, &contact.email, INITIAL_HISTORY_LIMIT)
+        let (messages, history_start_reached, history_end_reached) = if let Some(message_id) =
+            focused_message
+            && let Some(message_history) =
+                Self::messages_around(&sqlite, &user_email, &contact.email, message_id)
         {
-            message_history.reverse();
             message_history
+        } else if let Ok(mut message_history) =
+            sqlite.select_messages(&user_email, &contact.email, None, INITIAL_HISTORY_LIMIT)
+        {
+            message_history.reverse();
+            let history_start_reached = message_history.len() < INITIAL_HISTORY_LIMIT as usize;
+            (message_history, history_start_reached, true)
         } else {
-            Vec::new()
+            (Vec::new(), true, true)
         };
 
         let _ = main_window_sender.send(main_window::Message::ContactChatWindowFocused(
@@ -120,10 +146,23 @@ impl Conversation {
             italic: false,
             underline: false,
             strikethrough: false,
+            color: [0, 0, 0],
             focused: false,
             handle,
             viewport_id,
             invite_window: None,
+            focused_message,
+            scroll_to_focused_message: focused_message.is_some(),
+            history_start_reached,
+            history_end_reached,
+            previous_content_height: None,
+            older_messages_cursor: None,
+            newer_messages_cursor: None,
+            next_outbox_check: 0,
+            queued_messages: HashSet::new(),
+            queued_group_messages: Vec::new(),
+            resend_message: None,
+            confirm_clear_history: false,
         }
     }
 
@@ -142,15 +181,21 @@ impl Conversation {
         viewport_id: egui::viewport::ViewportId,
     ) -> Self {
         let messages = if switchboard.participants.len() > 1
-            && let Ok(mut message_history) =
-                sqlite.select_messages_by_session_id(&session_id, INITIAL_HISTORY_LIMIT)
-        {
+            && let Ok(mut message_history) = sqlite.select_group_messages(
+                &user_email,
+                &participants_key(
+                    switchboard.participants.iter().map(|email| email.as_str()),
+                    &user_email,
+                ),
+                None,
+                INITIAL_HISTORY_LIMIT,
+            ) {
             message_history.reverse();
             message_history
         } else if switchboard.participants.len() == 1
             && let Some(participant) = switchboard.participants.first()
             && let Ok(mut message_history) =
-                sqlite.select_messages(&user_email, participant, INITIAL_HISTORY_LIMIT)
+                sqlite.select_messages(&user_email, participant, None, INITIAL_HISTORY_LIMIT)
         {
             message_history.reverse();
             message_history
@@ -158,6 +203,8 @@ impl Conversation {
             Vec::new()
         };
 
+        let history_start_reached = messages.len() < INITIAL_HISTORY_LIMIT as usize;
+
         let mut participants = BTreeMap::new();
         for participant in &switchboard.participants {
             participants.insert(
@@ -202,10 +249,23 @@ impl Conversation {
             italic: false,
             underline: false,
             strikethrough: false,
+            color: [0, 0, 0],
             focused: false,
             handle,
             viewport_id,
             invite_window: None,
+            focused_message: None,
+            scroll_to_focused_message: false,
+            history_start_reached,
+            history_end_reached: true,
+            previous_content_height: None,
+            older_messages_cursor: None,
+            newer_messages_cursor: None,
+            next_outbox_check: 0,
+            queued_messages: HashSet::new(),
+            queued_group_messages: Vec::new(),
+            resend_message: None,
+            confirm_clear_history: false,
         }
     }
 
@@ -271,56 +331,34 @@ impl Conversation {
                             && let Ok(mut message_history) = self.sqlite.select_messages(
                                 &self.user_email,
                                 &email,
+                                None,
                                 INITIAL_HISTORY_LIMIT,
                             )
                         {
                             message_history.reverse();
+                            self.history_start_reached =
+                                message_history.len() < INITIAL_HISTORY_LIMIT as usize;
+                            self.history_end_reached = true;
                             self.messages = message_history;
-                        }
-
-                        if !self.message_buffer.is_empty()
-                            && let Some(switchboard) = self.switchboards.get(&session_id)
+                        } else if let Some(participants) = self.group_participants()
+                            && self.messages.iter().all(|message| message.is_history)
+                            && let Ok(mut message_history) = self.sqlite.select_group_messages(
+                                &self.user_email,
+                                &participants,
+                                None,
+                                INITIAL_HISTORY_LIMIT,
+                            )
                         {
-                            for mut message in self.message_buffer.drain(..) {
-                                let switchboard = switchboard.clone();
-                                let sender = self.sender.clone();
-
-                                if !message.is_nudge {
-                                    let plain_text = msnp11_sdk::PlainText {
-                                        bold: message.bold,
-                                        italic: message.italic,
-                                        underline: message.underline,
-                                        strikethrough: message.strikethrough,
-                                        color: message.color.clone(),
-                                        text: message.text.clone(),
-                                    };
-
-                                    run_future(
-                                        self.handle.clone(),
-                                        async move { switchboard.send_text_message(&plain_text).await },
-                                        sender,
-                                        move |result| {
-                                            Message::SendMessageResult(
-                                                std::mem::take(&mut message),
-                                                result,
-                                            )
-                                        },
-                                    );
-                                } else {
-                                    run_future(
-                                        self.handle.clone(),
-                                        async move { switchboard.send_nudge().await },
-                                        sender,
-                                        move |result| {
-                                            Message::SendMessageResult(
-                                                std::mem::take(&mut message),
-                                                result,
-                                            )
-                                        },
-                                    );
-                                }
-                            }
+                            // Someone was invited before anything was said, so this is now the
+                            // group's conversation
+                            message_history.reverse();
+                            self.history_start_reached =
+                                message_history.len() < INITIAL_HISTORY_LIMIT as usize;
+                            self.history_end_reached = true;
+                            self.messages = message_history;
                         }
+
+                        self.send_outbox_messages(true);
                     }
 
                     msnp11_sdk::Event::ParticipantLeftSwitchboard { email } => {
@@ -335,7 +373,7 @@ impl Conversation {
                     {
                         self.participant_typing =
                             if let Some(participant) = self.participants.get(&email) {
-                                Some(participant.display_name.clone())
+                                Some(participant.name().clone())
                             } else {
                                 Some(Arc::new(email))
                             };
@@ -351,9 +389,14 @@ impl Conversation {
                     }
 
                     msnp11_sdk::Event::TextMessage { email, message } => {
-                        let message = message::Message {
+                        let mut message = message::Message {
+                            id: None,
                             sender: Arc::new(email),
-                            receiver: Some(self.user_email.clone()),
+                            receiver: if self.participants.len() > 1 {
+                                None
+                            } else {
+                                Some(self.user_email.clone())
+                            },
                             is_nudge: false,
                             text: message.text,
                             bold: message.bold,
@@ -361,12 +404,17 @@ impl Conversation {
                             underline: message.underline,
                             strikethrough: message.strikethrough,
                             session_id: None,
+                            participants: self.group_participants(),
                             color: message.color,
                             is_history: false,
                             errored: false,
+                            timestamp: chrono::Local::now().timestamp(),
                         };
 
-                        let _ = self.sqlite.insert_message(&message);
+                        if let Ok(id) = self.sqlite.insert_message(&self.user_email, &message) {
+                            message.id = Some(id);
+                        }
+
                         if !self.focused {
                             if self.user_status != MsnpStatus::Busy {
                                 let _ = notify_rust::Notification::new()
@@ -375,11 +423,11 @@ impl Conversation {
                                         if let Some(participant) =
                                             self.participants.get(&message.sender)
                                         {
-                                            &participant.display_name
+                                            participant.name().as_str()
                                         } else if let Some(participant) = &self.last_participant
                                             && participant.email == message.sender
                                         {
-                                            &*participant.display_name
+                                            participant.name().as_str()
                                         } else {
                                             &message.sender
                                         }
@@ -396,24 +444,29 @@ impl Conversation {
                             );
                         }
 
-                        self.messages.push(message);
+                        self.push_message(message);
                         self.participant_typing = None;
                     }
 
                     msnp11_sdk::Event::Nudge { email } => {
                         let sender = Arc::new(email);
-                        let message = message::Message {
+                        let mut message = message::Message {
+                            id: None,
                             sender: sender.clone(),
-                            receiver: Some(self.user_email.clone()),
+                            receiver: if self.participants.len() > 1 {
+                                None
+                            } else {
+                                Some(self.user_email.clone())
+                            },
                             is_nudge: true,
                             text: format!(
                                 "{} just sent you a nudge!",
                                 if let Some(participant) = self.participants.get(&sender) {
-                                    &participant.display_name
+                                    participant.name().as_str()
                                 } else if let Some(participant) = &self.last_participant
                                     && participant.email == sender
                                 {
-                                    &*participant.display_name
+                                    participant.name().as_str()
                                 } else {
                                     &sender
                                 }
@@ -423,12 +476,17 @@ impl Conversation {
                             underline: false,
                             strikethrough: false,
                             session_id: None,
+                            participants: self.group_participants(),
                             color: "0".to_string(),
                             is_history: false,
                             errored: false,
+                            timestamp: chrono::Local::now().timestamp(),
                         };
 
-                        let _ = self.sqlite.insert_message(&message);
+                        if let Ok(id) = self.sqlite.insert_message(&self.user_email, &message) {
+                            message.id = Some(id);
+                        }
+
                         if !self.focused {
                             if self.user_status != MsnpStatus::Busy {
                                 let _ = notify_rust::Notification::new()
@@ -445,7 +503,7 @@ impl Conversation {
                             );
                         }
 
-                        self.messages.push(message);
+                        self.push_message(message);
                         self.participant_typing = None;
                     }
 
@@ -461,6 +519,26 @@ impl Conversation {
                 self.user_status = status;
             }
 
+            main_window::Message::ContactNameChanged(email) => {
+                let Some(updated) = self.contact_repository.get_contact(&email) else {
+                    return;
+                };
+
+                let contact = if let Some(contact) = self.participants.get_mut(&email) {
+                    Some(contact)
+                } else {
+                    self.last_participant
+                        .as_mut()
+                        .filter(|contact| contact.email == email)
+                };
+
+                if let Some(contact) = contact {
+                    contact.display_name = updated.display_name;
+                    contact.alias = updated.alias;
+                    contact.friendly_name = updated.friendly_name;
+                }
+            }
+
             main_window::Message::ContactDisplayPictureEvent { email, data } => {
                 if let Some(contact) = self.participants.get_mut(&email)
                     && let Some(presence) = &contact.status
@@ -510,13 +588,32 @@ impl Conversation {
         if let Ok(message) = self.receiver.try_recv() {
             match message {
                 Message::SendMessageResult(mut message, result) => {
-                    if result.is_err() {
-                        message.errored = true;
-                    } else {
-                        let _ = self.sqlite.insert_message(&message);
+                    message.errored = result.is_err();
+                    if let Some(id) = message.id {
+                        // Sent from the outbox, which already counted the attempt
+                        outbox::finish_attempt(&self.sqlite, id, result.is_ok());
+                        if result.is_ok() {
+                            self.queued_messages.remove(&id);
+                        }
+                    } else if let Ok(id) = self.sqlite.insert_message(&self.user_email, &message) {
+                        message.id = Some(id);
+                        if message.errored && message.receiver.is_some() {
+                            let next_attempt =
+                                chrono::Local::now().timestamp() + outbox::retry_delay(1);
+
+                            let _ = self.sqlite.schedule_outbox_message(id, 1, next_attempt);
+                            self.next_outbox_check = self.next_outbox_check.min(next_attempt);
+                            self.queued_messages.insert(id);
+                        }
                     }
 
-                    self.messages.push(message);
+                    if let Some(sent_message) = self.messages.iter_mut().find(|sent_message| {
+                        sent_message.id.is_some() && sent_message.id == message.id
+                    }) {
+                        sent_message.errored = message.errored;
+                    } else {
+                        self.push_message(message);
+                    }
                 }
 
                 Message::CreateSessionResult(result) => match result {
@@ -574,6 +671,52 @@ impl Conversation {
             }
         }
 
+        // Messages typed before anyone joined are queued. A contact's wait in the outbox, so they
+        // aren't lost on restarts
+        let now = chrono::Local::now().timestamp();
+        for message in std::mem::take(&mut self.message_buffer) {
+            let mut message = message::Message {
+                errored: true,
+                ..message
+            };
+
+            if let Ok(id) = self.sqlite.insert_message(&self.user_email, &message) {
+                message.id = Some(id);
+
+                // Group messages have no outbox, so they wait for this session's participants
+                if message.receiver.is_some() {
+                    let next_attempt = now + outbox::retry_delay(1);
+                    let _ = self.sqlite.schedule_outbox_message(id, 0, next_attempt);
+                    self.next_outbox_check = self.next_outbox_check.min(next_attempt);
+                } else {
+                    self.queued_group_messages.push(message.clone());
+                }
+
+                self.queued_messages.insert(id);
+
+                self.push_message(message);
+            }
+        }
+
+        if now >= self.next_outbox_check {
+            self.send_outbox_messages(false);
+        }
+
+        if !self.queued_group_messages.is_empty() && !self.participants.is_empty() {
+            self.send_queued_group_messages();
+        }
+
+        if let Some(message_id) = self.resend_message.take() {
+            self.resend(message_id);
+        }
+
+        if self.next_outbox_check != i64::MAX {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs(
+                    (self.next_outbox_check - now).max(1) as u64,
+                ));
+        }
+
         egui::Panel::right("display_pictures")
             .frame(egui::Frame {
                 inner_margin: egui::Margin {
@@ -656,6 +799,7 @@ impl Conversation {
                     ..Default::default()
                 })
                 .show(|tui| {
+                    let mut real_name = None;
                     let mut job = LayoutJob::default();
                     job.append(
                         "To: ",
@@ -670,8 +814,9 @@ impl Conversation {
                     if self.participants.len() == 1
                         && let Some(contact) = self.participants.values().next()
                     {
+                        real_name = contact.real_name().cloned();
                         job.append(
-                            &contact.display_name,
+                            contact.name(),
                             0.,
                             TextFormat {
                                 font_id: FontId::proportional(14.),
@@ -700,8 +845,9 @@ impl Conversation {
                             },
                         );
                     } else if let Some(contact) = &self.last_participant {
+                        real_name = contact.real_name().cloned();
                         job.append(
-                            &contact.display_name,
+                            contact.name(),
                             0.,
                             TextFormat {
                                 font_id: FontId::proportional(14.),
@@ -748,33 +894,98 @@ impl Conversation {
                                 }
                             }
 
-                            ui.label(job);
+                            let to = ui.label(job);
+                            if let Some(real_name) = real_name {
+                                to.on_hover_text(real_name.as_str());
+                            }
                         });
 
                         ui.add_space(5.);
+                        let contact_email = self
+                            .participants
+                            .values()
+                            .next()
+                            .or(self.last_participant.as_ref())
+                            .map(|contact| contact.email.clone());
+
                         if self.participants.len() < 2
-                            && ui
-                                .link("Load your entire conversation history with this contact")
-                                .clicked()
-                            && let Some(participant) = self.participants.values().next()
-                            && let Ok(message_history) = self
-                                .sqlite
-                                .select_all_messages(&self.user_email, &participant.email)
+                            && let Some(contact_email) = contact_email
                         {
-                            self.messages = message_history;
+                            ui.horizontal(|ui| {
+                                if self.confirm_clear_history {
+                                    ui.label("Delete all messages with this contact?");
+                                    if ui.link("Delete").clicked() {
+                                        self.confirm_clear_history = false;
+                                        if self
+                                            .sqlite
+                                            .delete_transcript_messages(
+                                                &self.user_email,
+                                                &TranscriptKind::Contact(contact_email.clone()),
+                                            )
+                                            .is_ok()
+                                        {
+                                            self.messages.clear();
+                                            self.history_start_reached = true;
+                                            self.history_end_reached = true;
+                                        }
+                                    }
+
+                                    if ui.link("Cancel").clicked() {
+                                        self.confirm_clear_history = false;
+                                    }
+                                } else {
+                                    if ui
+                                        .link("Load your entire conversation history with this contact")
+                                        .clicked()
+                                        && let Ok(message_history) = self
+                                            .sqlite
+                                            .select_all_messages(&self.user_email, &contact_email)
+                                    {
+                                        self.messages = message_history;
+                                        self.history_start_reached = true;
+                                        self.history_end_reached = true;
+                                    }
+
+                                    ui.add_space(10.);
+                                    if ui
+                                        .link("Clear history")
+                                        .on_hover_text("Delete your stored conversation history with this contact")
+                                        .clicked()
+                                    {
+                                        self.confirm_clear_history = true;
+                                    }
+                                }
+                            });
                         }
 
                         ui.separator();
                     });
 
-                    messages(
+                    let mut end_of_view_reached = false;
+                    if let Some(content_height) = messages(
                         tui,
                         &self.participants,
                         self.last_participant.clone(),
                         self.user_email.clone(),
                         self.user_display_name.clone(),
                         &self.messages,
-                    );
+                        self.focused_message,
+                        &mut self.scroll_to_focused_message,
+                        self.history_start_reached,
+                        self.history_end_reached,
+                        &mut end_of_view_reached,
+                        &mut self.previous_content_height,
+                        &self.queued_messages,
+                        &mut self.resend_message,
+                    ) && !self.history_start_reached
+                        && self.load_older_messages()
+                    {
+                        self.previous_content_height = Some(content_height);
+                    }
+
+                    if end_of_view_reached && !self.history_end_reached {
+                        self.load_newer_messages();
+                    }
 
                     tui.style(taffy::Style {
                         grid_row: line(4),
@@ -785,11 +996,11 @@ impl Conversation {
                             let display_name = if let Some(participant) =
                                 self.participants.get(&typing_participant)
                             {
-                                &participant.display_name
+                                participant.name().as_str()
                             } else if let Some(participant) = &self.last_participant
                                 && participant.email == typing_participant
                             {
-                                &*participant.display_name
+                                participant.name().as_str()
                             } else {
                                 &typing_participant
                             };
@@ -814,6 +1025,7 @@ impl Conversation {
                         &mut self.italic,
                         &mut self.underline,
                         &mut self.strikethrough,
+                        &mut self.color,
                         &mut self.new_message,
                     );
                 });
@@ -843,6 +1055,341 @@ impl Conversation {
         &self.last_participant
     }
 
+    // The key group messages are stored under, if more than one contact is in the conversation
+    fn group_participants(&self) -> Option<Arc<String>> {
+        (self.participants.len() > 1).then(|| {
+            Arc::new(participants_key(
+                self.participants.keys().map(|email| email.as_str()),
+                &self.user_email,
+            ))
+        })
+    }
+
+    // The contact whose outbox this conversation sends, which group conversations have none of
+    pub fn outbox_contact(&self) -> Option<Arc<String>> {
+        if self.participants.len() > 1 {
+            return None;
+        }
+
+        self.participants
+            .values()
+            .next()
+            .or(self.last_participant.as_ref())
+            .map(|contact| contact.email.clone())
+    }
+
+    // Sends the contact's messages waiting in the outbox that are due, or all of them that aren't
+    // already being sent if forced. Group conversations have no outbox, their messages are only
+    // queued until someone joins, and resent by hand after that
+    fn send_outbox_messages(&mut self, force: bool) {
+        let now = chrono::Local::now().timestamp();
+        self.next_outbox_check = i64::MAX;
+
+        let Some(contact) = self.outbox_contact() else {
+            return;
+        };
+
+        let Ok(entries) = self.sqlite.select_outbox(&self.user_email, &contact) else {
+            return;
+        };
+
+        self.queued_messages = entries
+            .iter()
+            .filter(|entry| entry.attempts < outbox::MAX_ATTEMPTS)
+            .filter_map(|entry| entry.message.id)
+            .collect();
+
+        let (due_entries, next_attempt) = outbox::due_entries(entries, force, now);
+        self.next_outbox_check = next_attempt;
+
+        if due_entries.is_empty() {
+            return;
+        }
+
+        let Some(switchboard) = self.switchboards.values().next().cloned() else {
+            self.next_outbox_check = now + outbox::IDLE_CHECK;
+            return;
+        };
+
+        // Everything is sent once the contact joins
+        if self.participants.is_empty() {
+            self.handle
+                .spawn(async move { switchboard.invite(&contact).await });
+
+            self.next_outbox_check = now + outbox::IDLE_CHECK;
+            return;
+        }
+
+        for mut entry in due_entries {
+            let Some(id) = entry.message.id else {
+                continue;
+            };
+
+            if let Some(next_attempt) = outbox::start_attempt(&self.sqlite, &entry, now) {
+                self.next_outbox_check = self.next_outbox_check.min(next_attempt);
+            } else {
+                self.queued_messages.remove(&id);
+            }
+
+            entry.message.is_history = false;
+            self.send_to_switchboard(switchboard.clone(), entry.message);
+        }
+    }
+
+    // Sends the group messages typed before anyone joined to the participants there are now
+    fn send_queued_group_messages(&mut self) {
+        let Some(switchboard) = self.switchboards.values().next().cloned() else {
+            return;
+        };
+
+        let receiver = self.outbox_contact();
+        let participants = self.group_participants();
+        for message in std::mem::take(&mut self.queued_group_messages) {
+            let Some(id) = message.id else {
+                continue;
+            };
+
+            // They were stored without recipients, so they're moved into the right transcript
+            let _ = self.sqlite.update_message_recipients(
+                id,
+                receiver.as_ref().map(|receiver| receiver.as_str()),
+                participants
+                    .as_ref()
+                    .map(|participants| participants.as_str()),
+            );
+
+            self.queued_messages.remove(&id);
+            let message = message::Message {
+                receiver: receiver.clone(),
+                participants: participants.clone(),
+                is_history: false,
+                ..message
+            };
+
+            self.send_to_switchboard(switchboard.clone(), message);
+        }
+    }
+
+    fn resend(&mut self, message_id: i64) {
+        if self.outbox_contact().is_some() {
+            let _ = self.sqlite.schedule_outbox_message(
+                message_id,
+                0,
+                chrono::Local::now().timestamp(),
+            );
+
+            self.send_outbox_messages(true);
+        } else if !self.participants.is_empty()
+            && let Some(switchboard) = self.switchboards.values().next().cloned()
+            && let Some(message) = self
+                .messages
+                .iter()
+                .find(|message| message.id == Some(message_id))
+        {
+            let message = message::Message {
+                is_history: false,
+                ..message.clone()
+            };
+
+            self.send_to_switchboard(switchboard, message);
+        }
+    }
+
+    fn send_to_switchboard(&self, switchboard: Arc<Switchboard>, message: message::Message) {
+        run_future(
+            self.handle.clone(),
+            async move {
+                let result = outbox::send(&switchboard, &message).await;
+                (message, result)
+            },
+            self.sender.clone(),
+            |(message, result)| Message::SendMessageResult(message, result),
+        );
+    }
+
+    // Inserts the page of messages before the oldest one loaded, returning whether any were found
+    fn load_older_messages(&mut self) -> bool {
+        let Some((oldest_message, id)) = self
+            .messages
+            .iter()
+            .find_map(|message| message.id.map(|id| (message, id)))
+        else {
+            self.history_start_reached = true;
+            return false;
+        };
+
+        let before = Some((oldest_message.timestamp, id));
+        if before == self.older_messages_cursor {
+            return false;
+        }
+
+        self.older_messages_cursor = before;
+        let older_messages = if let Some(participants) = &oldest_message.participants {
+            self.sqlite.select_group_messages(
+                &self.user_email,
+                participants,
+                before,
+                HISTORY_PAGE_SIZE,
+            )
+        } else if let Some(contact) = self
+            .participants
+            .values()
+            .next()
+            .or(self.last_participant.as_ref())
+        {
+            self.sqlite
+                .select_messages(&self.user_email, &contact.email, before, HISTORY_PAGE_SIZE)
+        } else {
+            Ok(Vec::new())
+        };
+
+        let Ok(mut older_messages) = older_messages else {
+            return false;
+        };
+
+        self.history_start_reached = older_messages.len() < HISTORY_PAGE_SIZE as usize;
+        if older_messages.is_empty() {
+            return false;
+        }
+
+        older_messages.reverse();
+        older_messages.append(&mut self.messages);
+        self.messages = older_messages;
+        true
+    }
+
+    // Appends the page of messages after the newest one loaded, when a search hit left later ones out
+    fn load_newer_messages(&mut self) {
+        let Some(after) = self
+            .messages
+            .iter()
+            .rev()
+            .find_map(|message| message.id.map(|id| (message.timestamp, id)))
+        else {
+            self.history_end_reached = true;
+            return;
+        };
+
+        if Some(after) == self.newer_messages_cursor {
+            return;
+        }
+
+        self.newer_messages_cursor = Some(after);
+        let Some(contact_email) = self
+            .participants
+            .values()
+            .next()
+            .or(self.last_participant.as_ref())
+            .map(|contact| contact.email.clone())
+        else {
+            return;
+        };
+
+        if let Ok(mut newer_messages) = self.sqlite.select_messages_after(
+            &self.user_email,
+            &contact_email,
+            after,
+            HISTORY_PAGE_SIZE,
+        ) {
+            self.history_end_reached = newer_messages.len() < HISTORY_PAGE_SIZE as usize;
+            self.messages.append(&mut newer_messages);
+        }
+    }
+
+    // New messages go after the latest history, so if a search hit left that out it's loaded first
+    fn push_message(&mut self, message: message::Message) {
+        if !self.history_end_reached
+            && let Some(contact_email) = self
+                .participants
+                .values()
+                .next()
+                .or(self.last_participant.as_ref())
+                .map(|contact| contact.email.clone())
+            && let Ok(mut message_history) = self.sqlite.select_messages(
+                &self.user_email,
+                &contact_email,
+                None,
+                INITIAL_HISTORY_LIMIT,
+            )
+        {
+            // The message was stored already, so the live copy replaces the one just loaded
+            message_history.retain(|stored_message| stored_message.id != message.id);
+            message_history.reverse();
+            self.history_start_reached = message_history.len() < INITIAL_HISTORY_LIMIT as usize;
+            self.older_messages_cursor = None;
+            self.messages = message_history;
+        }
+
+        self.history_end_reached = true;
+        self.newer_messages_cursor = None;
+        self.messages.push(message);
+    }
+
+    // A page of messages on each side of a search hit, with whether they reach the start and end of
+    // the history. The rest is paged in while scrolling
+    fn messages_around(
+        sqlite: &Sqlite,
+        user_email: &str,
+        contact_email: &str,
+        message_id: i64,
+    ) -> Option<(Vec<message::Message>, bool, bool)> {
+        let timestamp = sqlite
+            .select_message_timestamp(user_email, message_id)
+            .ok()?;
+
+        // Includes the hit itself
+        let mut messages = sqlite
+            .select_messages(
+                user_email,
+                contact_email,
+                Some((timestamp, message_id + 1)),
+                HISTORY_PAGE_SIZE,
+            )
+            .ok()?;
+
+        let history_start_reached = messages.len() < HISTORY_PAGE_SIZE as usize;
+        messages.reverse();
+
+        let mut newer_messages = sqlite
+            .select_messages_after(
+                user_email,
+                contact_email,
+                (timestamp, message_id),
+                HISTORY_PAGE_SIZE,
+            )
+            .ok()?;
+
+        let history_end_reached = newer_messages.len() < HISTORY_PAGE_SIZE as usize;
+        messages.append(&mut newer_messages);
+        Some((messages, history_start_reached, history_end_reached))
+    }
+
+    pub fn focus_message(&mut self, message_id: i64) {
+        let contact = if self.participants.len() == 1 {
+            self.participants.values().next()
+        } else {
+            self.last_participant.as_ref()
+        };
+
+        if !self
+            .messages
+            .iter()
+            .any(|message| message.id == Some(message_id))
+            && let Some(contact) = contact
+            && let Some((message_history, history_start_reached, history_end_reached)) =
+                Self::messages_around(&self.sqlite, &self.user_email, &contact.email, message_id)
+        {
+            self.messages = message_history;
+            self.history_start_reached = history_start_reached;
+            self.history_end_reached = history_end_reached;
+            self.older_messages_cursor = None;
+            self.newer_messages_cursor = None;
+        }
+
+        self.focused_message = Some(message_id);
+        self.scroll_to_focused_message = true;
+    }
+
     pub fn add_switchboard(
         &mut self,
         session_id: Arc<String>,
@@ -864,7 +1411,7 @@ impl Conversation {
         if !self.participants.is_empty() {
             let mut title = "".to_string();
             for participant in self.participants.values() {
-                title.push_str(&participant.display_name);
+                title.push_str(participant.name());
                 title.push_str(", ");
             }
 
@@ -873,7 +1420,7 @@ impl Conversation {
             title.push_str(" - Conversation");
             title
         } else if let Some(last_participant) = &self.last_participant {
-            format!("{} - Conversation", *last_participant.display_name)
+            format!("{} - Conversation", last_participant.name())
         } else {
             "Conversation".to_string()
         }
@@ -1,15 +1,18 @@
+use crate::helpers::msn_color::color_from_msn;
 use crate::models::contact::Contact;
 use crate::models::message;
-use crate::screens;
+use crate::{screens, visuals};
+use chrono::{Local, NaiveDate, TimeZone};
 use eframe::egui;
 use eframe::egui::text::LayoutJob;
 use eframe::egui::{FontId, FontSelection, TextFormat};
 use egui_taffy::taffy::prelude::{auto, percent, span};
 use egui_taffy::{Tui, TuiBuilderLogic, taffy};
 use regex::Regex;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
+#[allow(clippy::too_many_arguments)]
 pub fn messages(
     tui: &mut Tui,
     participants: &BTreeMap<Arc<String>, Contact>,
@@ -17,7 +20,15 @@ pub fn messages(
     user_email: Arc<String>,
     user_display_name: Arc<String>,
     messages: &[message::Message],
-) {
+    focused_message: Option<i64>,
+    scroll_to_focused_message: &mut bool,
+    start_of_history: bool,
+    end_of_history: bool,
+    end_of_view_reached: &mut bool,
+    previous_content_height: &mut Option<f32>,
+    queued_messages: &HashSet<i64>,
+    resend_message: &mut Option<i64>,
+) -> Option<f32> {
     tui.style(taffy::Style {
         justify_self: Some(taffy::JustifySelf::Start),
         size: taffy::Size {
@@ -27,71 +38,210 @@ pub fn messages(
         grid_row: span(2),
         ..Default::default()
     })
-        .ui(|ui| {
-            egui::ScrollArea::vertical().auto_shrink(false).stick_to_bottom(true).show(ui, |ui| {
+    .ui(|ui| {
+        let output = egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .stick_to_bottom(end_of_history)
+            .show(ui, |ui| {
+                if start_of_history {
+                    ui.vertical_centered(|ui| {
+                        ui.weak("Start of conversation history");
+                    });
+
+                    ui.add_space(5.);
+                }
+
+                let mut previous_date = None;
                 for message in messages.iter() {
-                    ui.with_layout(
-                        egui::Layout::top_down_justified(egui::Align::LEFT),
-                        |ui| {
-                            let display_name = if let Some(participant) =
-                                participants.get(&message.sender)
-                            {
-                                &participant.display_name
-                            } else if let Some(participant) = &last_participant
-                                && participant.email == message.sender
-                            {
-                                &*participant.display_name
-                            } else if message.sender == user_email {
-                                &user_display_name
-                            } else {
-                                &message.sender
-                            };
-
-                            if message.is_history {
-                                ui.style_mut().visuals.override_text_color =
-                                    Some(if ui.visuals().dark_mode {
-                                        egui::Color32::GRAY
-                                    } else {
-                                        egui::Color32::from_gray(120)
-                                    });
-                            }
+                    day_separator(ui, message.timestamp, &mut previous_date);
+                    let contact = participants.get(&message.sender).or(last_participant
+                        .as_ref()
+                        .filter(|participant| participant.email == message.sender));
 
-                            if !message.is_nudge && !message.errored {
-                                let id = ui
-                                    .label(format!("{} said:", display_name))
-                                    .id;
-
-                                ui.indent(id, |ui| {
-                                    display_text_message(ui, message, &screens::URL_REGEX, ui.visuals().text_color());
-                                });
-                            } else if message.errored {
-                                ui.separator();
-                                let id = ui
-                                    .label("The following message could not be delivered to all recipients:")
-                                    .id;
-
-                                ui.indent(id, |ui| {
-                                    display_text_message(ui, message, &screens::URL_REGEX, if ui.visuals().dark_mode {
-                                        egui::Color32::GRAY
-                                    } else {
-                                        egui::Color32::from_gray(120)
-                                    });
-                                });
-
-                                ui.separator();
-                            } else {
-                                // Nudge
-                                ui.separator();
-                                ui.label(&message.text);
-                                ui.separator();
+                    let display_name = if let Some(contact) = contact {
+                        contact.name().as_str()
+                    } else if message.sender == user_email {
+                        &user_display_name
+                    } else {
+                        &message.sender
+                    };
+
+                    let real_name = contact
+                        .and_then(|contact| contact.real_name())
+                        .map(|real_name| real_name.as_str());
+
+                    let is_focused = message.id.is_some() && message.id == focused_message;
+                    let response = egui::Frame::new()
+                        .fill(if is_focused {
+                            ui.visuals().selection.bg_fill
+                        } else {
+                            egui::Color32::TRANSPARENT
+                        })
+                        .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
+                        .show(ui, |ui| {
+                            let queued = message.id.is_some_and(|id| queued_messages.contains(&id));
+
+                            if display_message(ui, message, display_name, real_name, true, queued) {
+                                *resend_message = message.id;
                             }
-                        },
-                    );
+                        })
+                        .response;
+
+                    if is_focused && *scroll_to_focused_message {
+                        response.scroll_to_me(Some(egui::Align::Center));
+                        *scroll_to_focused_message = false;
+                    }
 
                     ui.add_space(5.);
                 }
             });
+
+        // Keeps the messages in view still while older ones are inserted above them
+        let mut state = output.state;
+        if let Some(previous_content_height) = previous_content_height.take() {
+            state.offset.y += output.content_size.y - previous_content_height;
+            state.store(ui.ctx(), output.id);
+            ui.ctx().request_discard("Older messages inserted");
+        }
+
+        // Newer messages are loaded when the bottom is reached, if a search hit left them out
+        *end_of_view_reached = !*scroll_to_focused_message
+            && state.offset.y + output.inner_rect.height() >= output.content_size.y - 1.;
+
+        // The content height lets the caller anchor the scroll position after loading more. This is
+        // returned on every frame the view is at the top, and the caller only loads each page once
+        (state.offset.y <= 0. && !*scroll_to_focused_message).then_some(output.content_size.y)
+    })
+}
+
+pub fn day_separator(ui: &mut egui::Ui, timestamp: i64, previous_date: &mut Option<NaiveDate>) {
+    // Messages from before timestamps were recorded are stored with 0 and get no separator
+    if timestamp != 0
+        && let Some(date_time) = Local.timestamp_opt(timestamp, 0).single()
+        && *previous_date != Some(date_time.date_naive())
+    {
+        *previous_date = Some(date_time.date_naive());
+        ui.vertical_centered(|ui| {
+            ui.weak(date_time.format("%A, %B %-d, %Y").to_string());
         });
+
+        ui.add_space(5.);
+    }
+}
+
+// Returns whether resending the message was requested, which is only offered if can_resend is set.
+// Queued messages are undelivered ones the outbox is still retrying. The real name is shown on hover
+// when the display name is an alias.
+pub fn display_message(
+    ui: &mut egui::Ui,
+    message: &message::Message,
+    display_name: &str,
+    real_name: Option<&str>,
+    can_resend: bool,
+    queued: bool,
+) -> bool {
+    let mut resend = false;
+    let date_time = if message.timestamp != 0 {
+        Local.timestamp_opt(message.timestamp, 0).single()
+    } else {
+        None
+    };
+
+    let time = date_time
+        .map(|date_time| date_time.format("%H:%M").to_string())
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let full_date = date_time
+        .map(|date_time| date_time.format("%A, %B %-d, %Y %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Unknown date".to_string());
+
+    ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+        if message.is_history {
+            ui.style_mut().visuals.override_text_color = Some(if ui.visuals().dark_mode {
+                egui::Color32::GRAY
+            } else {
+                egui::Color32::from_gray(120)
+            });
+        }
+
+        if !message.is_nudge && !message.errored {
+            let id = ui
+                .horizontal(|ui| {
+                    let mut sender = ui.label(format!("{} said:", display_name));
+                    if let Some(real_name) = real_name {
+                        sender = sender.on_hover_text(real_name);
+                    }
+
+                    let id = sender.id;
+                    ui.weak(format!(" {time}")).on_hover_text(&full_date);
+                    id
+                })
+                .inner;
+
+            let text_color = if let Some(color) = color_from_msn(&message.color) {
+                let color = visuals::readable_message_color(color, ui.visuals().dark_mode);
+                if message.is_history {
+                    color.gamma_multiply(0.6)
+                } else {
+                    color
+                }
+            } else {
+                ui.visuals().text_color()
+            };
+
+            ui.indent(id, |ui| {
+                display_text_message(ui, message, &screens::URL_REGEX, text_color);
+            });
+        } else if message.errored {
+            ui.separator();
+            let id = ui
+                .label(if queued {
+                    "The following message is waiting to be sent:"
+                } else {
+                    "The following message could not be delivered to all recipients:"
+                })
+                .on_hover_text(&full_date)
+                .id;
+
+            ui.indent(id, |ui| {
+                display_text_message(
+                    ui,
+                    message,
+                    &screens::URL_REGEX,
+                    if ui.visuals().dark_mode {
+                        egui::Color32::GRAY
+                    } else {
+                        egui::Color32::from_gray(120)
+                    },
+                );
+            });
+
+            if can_resend && message.id.is_some() {
+                ui.horizontal(|ui| {
+                    resend = if queued {
+                        ui.link("Send Now")
+                            .on_hover_text("Try sending this message now instead of waiting")
+                            .clicked()
+                    } else {
+                        ui.link("Resend")
+                            .on_hover_text("Try sending this message again")
+                            .clicked()
+                    };
+                });
+            }
+
+            ui.separator();
+        } else {
+            // Nudge
+            ui.separator();
+            ui.label(format!("{} ({time})", message.text))
+                .on_hover_text(&full_date);
+
+            ui.separator();
+        }
+    });
+
+    resend
 }
 
 fn display_text_message(
@@ -1,5 +1,5 @@
 mod contacts_display_pictures;
 #[allow(clippy::module_inception)]
 pub mod conversation;
-mod messages;
+pub mod messages;
 mod new_message_editor;
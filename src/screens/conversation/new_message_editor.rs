@@ -1,7 +1,10 @@
+use crate::helpers::msn_color::color_to_msn;
 use crate::helpers::run_future::run_future;
 use crate::models::contact::Contact;
 use crate::models::message;
+use crate::models::transcript::participants_key;
 use crate::screens::conversation::conversation::Message;
+use crate::visuals;
 use eframe::egui;
 use eframe::egui::text::LayoutJob;
 use eframe::egui::{FontId, FontSelection, TextFormat};
@@ -27,6 +30,7 @@ pub fn new_message_editor(
     italic: &mut bool,
     underline: &mut bool,
     strikethrough: &mut bool,
+    color: &mut [u8; 3],
     new_message: &mut String,
 ) {
     tui.style(taffy::Style {
@@ -42,6 +46,7 @@ pub fn new_message_editor(
                 .clicked()
             {
                 let mut message = message::Message {
+                    id: None,
                     sender: user_email.clone(),
                     receiver: if participants.len() == 1 {
                         participants
@@ -62,9 +67,16 @@ pub fn new_message_editor(
                     underline: false,
                     strikethrough: false,
                     session_id: None,
+                    participants: (participants.len() > 1).then(|| {
+                        Arc::new(participants_key(
+                            participants.keys().map(|email| email.as_str()),
+                            &user_email,
+                        ))
+                    }),
                     color: "0".to_string(),
                     is_history: false,
                     errored: false,
+                    timestamp: chrono::Local::now().timestamp(),
                 };
 
                 if let Some(switchboard) = switchboards.values().next() {
@@ -107,6 +119,10 @@ pub fn new_message_editor(
 
             ui.toggle_value(strikethrough, "S")
                 .on_hover_text("Toggle strikethrough");
+
+            // MSN colors have no alpha
+            ui.color_edit_button_srgb(color)
+                .on_hover_text("Choose the color of your messages");
         });
     });
 
@@ -130,7 +146,14 @@ pub fn new_message_editor(
                         } else {
                             FontSelection::Default.resolve(ui.style())
                         },
-                        color: ui.visuals().text_color(),
+                        color: if *color == [0, 0, 0] {
+                            ui.visuals().text_color()
+                        } else {
+                            visuals::readable_message_color(
+                                egui::Color32::from_rgb(color[0], color[1], color[2]),
+                                ui.visuals().dark_mode,
+                            )
+                        },
                         italics: *italic,
                         underline: if *underline {
                             ui.visuals().window_stroke
@@ -187,6 +210,7 @@ pub fn new_message_editor(
                 && !new_message.trim().is_empty()
             {
                 let mut message = message::Message {
+                    id: None,
                     sender: user_email.clone(),
                     receiver: if participants.len() == 1 {
                         participants
@@ -207,9 +231,16 @@ pub fn new_message_editor(
                     underline: *underline,
                     strikethrough: *strikethrough,
                     session_id: None,
-                    color: "0".to_string(),
+                    participants: (participants.len() > 1).then(|| {
+                        Arc::new(participants_key(
+                            participants.keys().map(|email| email.as_str()),
+                            &user_email,
+                        ))
+                    }),
+                    color: color_to_msn(egui::Color32::from_rgb(color[0], color[1], color[2])),
                     is_history: false,
                     errored: false,
+                    timestamp: chrono::Local::now().timestamp(),
                 };
 
                 let plain_text = msnp11_sdk::PlainText {
@@ -0,0 +1,153 @@
+use crate::helpers::run_future::run_future;
+use crate::models::group::Group;
+use crate::screens::contacts::contacts;
+use eframe::egui;
+use egui_taffy::taffy::prelude::{auto, length, percent};
+use egui_taffy::{TuiBuilderLogic, taffy, tui};
+use msnp11_sdk::Client;
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+// Asks for the name of a new group, or the new name of an existing one
+pub struct GroupName {
+    name: String,
+    group: Option<Group>,
+    client: Arc<Client>,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+}
+
+impl GroupName {
+    pub fn new(
+        group: Option<Group>,
+        client: Arc<Client>,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+        handle: Handle,
+    ) -> Self {
+        Self {
+            name: group
+                .as_ref()
+                .map(|group| group.name.to_string())
+                .unwrap_or_default(),
+            group,
+            client,
+            contacts_sender,
+            handle,
+        }
+    }
+
+    pub fn is_renaming(&self) -> bool {
+        self.group.is_some()
+    }
+
+    pub fn group_name(&mut self, ui: &mut egui::Ui) {
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame {
+                    fill: ui.visuals().window_fill,
+                    ..Default::default()
+                }
+                .inner_margin(5.),
+            )
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("group-name-screen"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
+                        align_items: Some(taffy::AlignItems::Stretch),
+                        size: taffy::Size {
+                            width: percent(0.9),
+                            height: auto(),
+                        },
+                        padding: length(20.),
+                        gap: length(15.),
+                        ..Default::default()
+                    })
+                    .show(|tui| {
+                        tui.ui(|ui| {
+                            let label = ui.label("Group name:");
+                            ui.add_space(3.);
+                            ui.add(
+                                egui::text_edit::TextEdit::singleline(&mut self.name)
+                                    .hint_text("Group name")
+                                    .min_size(egui::Vec2::new(340., 5.)),
+                            )
+                            .labelled_by(label.id);
+                        });
+
+                        tui.style(taffy::Style {
+                            align_self: Some(taffy::AlignItems::Center),
+                            size: taffy::Size {
+                                width: percent(0.2),
+                                height: auto(),
+                            },
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Ok").clicked() {
+                                    let name = Arc::new(self.name.trim().to_string());
+                                    let client = self.client.clone();
+
+                                    match &self.group {
+                                        Some(group) if !name.is_empty() => {
+                                            if let Some(guid) = group.guid.clone() {
+                                                let sent_guid = guid.clone();
+                                                let new_name = name.clone();
+                                                run_future(
+                                                    self.handle.clone(),
+                                                    async move {
+                                                        client
+                                                            .rename_group(&sent_guid, &new_name)
+                                                            .await
+                                                    },
+                                                    self.contacts_sender.clone(),
+                                                    move |result| {
+                                                        contacts::Message::RenameGroupResult {
+                                                            guid: guid.clone(),
+                                                            name: name.clone(),
+                                                            result,
+                                                        }
+                                                    },
+                                                );
+                                            }
+                                        }
+
+                                        None if !name.is_empty() => {
+                                            let new_name = name.clone();
+                                            run_future(
+                                                self.handle.clone(),
+                                                async move { client.create_group(&new_name).await },
+                                                self.contacts_sender.clone(),
+                                                move |result| {
+                                                    contacts::Message::CreateGroupResult(
+                                                        name.clone(),
+                                                        result,
+                                                    )
+                                                },
+                                            );
+                                        }
+
+                                        _ => (),
+                                    }
+
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseGroupName);
+                                }
+
+                                if ui.button("Cancel").clicked() {
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseGroupName);
+                                }
+                            })
+                        })
+                    })
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self.contacts_sender.send(contacts::Message::CloseGroupName);
+        }
+    }
+}
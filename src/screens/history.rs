@@ -0,0 +1,333 @@
+use crate::contact_repository::ContactRepository;
+use crate::helpers::run_future::run_future;
+use crate::models::message;
+use crate::models::transcript::{Transcript, TranscriptKind};
+use crate::screens::contacts::contacts;
+use crate::screens::conversation::messages;
+use crate::sqlite::Sqlite;
+use chrono::{Local, TimeZone};
+use eframe::egui;
+use r2d2_sqlite::rusqlite;
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+const PAGE_SIZE: u32 = 50;
+
+enum Message {
+    Transcripts(rusqlite::Result<Vec<Transcript>>),
+    Page(TranscriptKind, u32, rusqlite::Result<Vec<message::Message>>),
+}
+
+pub struct History {
+    transcripts: Option<Vec<Transcript>>,
+    selected_transcript: Option<Transcript>,
+    page: u32,
+    page_messages: Option<Vec<message::Message>>,
+    error: Option<String>,
+    user_email: Arc<String>,
+    user_display_name: Arc<String>,
+    contact_repository: ContactRepository,
+    sqlite: Sqlite,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+    sender: mpsc::Sender<Message>,
+    receiver: mpsc::Receiver<Message>,
+}
+
+impl History {
+    pub fn new(
+        user_email: Arc<String>,
+        user_display_name: Arc<String>,
+        contact_repository: ContactRepository,
+        sqlite: Sqlite,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+        handle: Handle,
+        ctx: egui::Context,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let history = Self {
+            transcripts: None,
+            selected_transcript: None,
+            page: 0,
+            page_messages: None,
+            error: None,
+            user_email,
+            user_display_name,
+            contact_repository,
+            sqlite,
+            contacts_sender,
+            handle,
+            sender,
+            receiver,
+        };
+
+        let sqlite = history.sqlite.clone();
+        let user_email = history.user_email.clone();
+        run_future(
+            history.handle.clone(),
+            async move {
+                let transcripts = sqlite.select_transcripts(&user_email);
+                ctx.request_repaint();
+                transcripts
+            },
+            history.sender.clone(),
+            Message::Transcripts,
+        );
+
+        history
+    }
+
+    pub fn history(&mut self, ui: &mut egui::Ui) {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                Message::Transcripts(result) => match result {
+                    Ok(transcripts) => self.transcripts = Some(transcripts),
+                    Err(error) => {
+                        self.transcripts = Some(Vec::new());
+                        self.error = Some(format!("Could not load history: {error}"));
+                    }
+                },
+
+                Message::Page(kind, page, result) => {
+                    // Ignore pages that arrive after the user moved on to another one
+                    if self
+                        .selected_transcript
+                        .as_ref()
+                        .is_some_and(|transcript| transcript.kind == kind)
+                        && self.page == page
+                    {
+                        match result {
+                            Ok(mut messages) => {
+                                // Everything here is history, so there's no need to dim it
+                                for message in &mut messages {
+                                    message.is_history = false;
+                                }
+
+                                self.page_messages = Some(messages);
+                            }
+                            Err(error) => {
+                                self.page_messages = Some(Vec::new());
+                                self.error = Some(format!("Could not load messages: {error}"));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        egui::Panel::left("history_transcripts")
+            .default_size(200.)
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(10),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        let Some(transcripts) = &self.transcripts else {
+                            ui.spinner();
+                            return;
+                        };
+
+                        if transcripts.is_empty() {
+                            ui.label("No conversations yet");
+                        }
+
+                        let mut selected = None;
+                        for transcript in transcripts {
+                            let is_selected = self
+                                .selected_transcript
+                                .as_ref()
+                                .is_some_and(|selected| selected.kind == transcript.kind);
+
+                            let last_date = Some(transcript.last_timestamp)
+                                .filter(|timestamp| *timestamp != 0)
+                                .and_then(|timestamp| Local.timestamp_opt(timestamp, 0).single())
+                                .map(|date_time| date_time.format("%B %-d, %Y").to_string())
+                                .unwrap_or_else(|| "an unknown date".to_string());
+
+                            if ui
+                                .selectable_label(is_selected, self.transcript_title(transcript))
+                                .on_hover_text(format!(
+                                    "{}{} messages, last on {last_date}",
+                                    if let TranscriptKind::Group(_) = transcript.kind {
+                                        transcript
+                                            .participants
+                                            .iter()
+                                            .map(|participant| format!("<{participant}>\n"))
+                                            .collect()
+                                    } else {
+                                        String::new()
+                                    },
+                                    transcript.message_count
+                                ))
+                                .clicked()
+                                && !is_selected
+                            {
+                                selected = Some(transcript.clone());
+                            }
+                        }
+
+                        if let Some(transcript) = selected {
+                            let last_page = transcript.message_count.saturating_sub(1) / PAGE_SIZE;
+                            self.selected_transcript = Some(transcript);
+                            self.load_page(ui.ctx().clone(), last_page);
+                        }
+                    });
+            });
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(10),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                    ui.add_space(5.);
+                }
+
+                if ui
+                    .button("Export All...")
+                    .on_hover_text("Export your whole conversation history to a file")
+                    .clicked()
+                {
+                    let _ = self
+                        .contacts_sender
+                        .send(contacts::Message::ExportHistory(None));
+                }
+
+                ui.add_space(5.);
+                let Some(transcript) = &self.selected_transcript else {
+                    ui.label("Select a conversation to view its history");
+                    return;
+                };
+
+                let page_count = transcript.message_count.div_ceil(PAGE_SIZE).max(1);
+                let mut new_page = None;
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.page > 0, egui::Button::new("Older"))
+                        .clicked()
+                    {
+                        new_page = Some(self.page - 1);
+                    }
+
+                    ui.label(format!("Page {} of {page_count}", self.page + 1));
+                    if ui
+                        .add_enabled(self.page + 1 < page_count, egui::Button::new("Newer"))
+                        .clicked()
+                    {
+                        new_page = Some(self.page + 1);
+                    }
+
+                    if let TranscriptKind::Contact(contact) = &transcript.kind
+                        && ui
+                            .button("Export...")
+                            .on_hover_text("Export the history with this contact to a file")
+                            .clicked()
+                    {
+                        let _ = self
+                            .contacts_sender
+                            .send(contacts::Message::ExportHistory(Some(contact.clone())));
+                    }
+                });
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .id_salt(("history_page", self.page))
+                    .show(ui, |ui| {
+                        let Some(page_messages) = &self.page_messages else {
+                            ui.spinner();
+                            return;
+                        };
+
+                        let mut previous_date = None;
+                        for message in page_messages {
+                            messages::day_separator(ui, message.timestamp, &mut previous_date);
+                            messages::display_message(
+                                ui,
+                                message,
+                                &self.display_name(&message.sender),
+                                self.real_name(&message.sender)
+                                    .as_deref()
+                                    .map(String::as_str),
+                                false,
+                                false,
+                            );
+
+                            ui.add_space(5.);
+                        }
+                    });
+
+                if let Some(page) = new_page {
+                    self.load_page(ui.ctx().clone(), page);
+                }
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self.contacts_sender.send(contacts::Message::CloseHistory);
+        }
+    }
+
+    fn load_page(&mut self, ctx: egui::Context, page: u32) {
+        let Some(transcript) = &self.selected_transcript else {
+            return;
+        };
+
+        self.page = page;
+        self.page_messages = None;
+        self.error = None;
+
+        let sqlite = self.sqlite.clone();
+        let user_email = self.user_email.clone();
+        let kind = transcript.kind.clone();
+        run_future(
+            self.handle.clone(),
+            async move {
+                let messages =
+                    sqlite.select_transcript_page(&user_email, &kind, page * PAGE_SIZE, PAGE_SIZE);
+
+                ctx.request_repaint();
+                (kind, messages)
+            },
+            self.sender.clone(),
+            move |(kind, messages)| Message::Page(kind, page, messages),
+        );
+    }
+
+    fn transcript_title(&self, transcript: &Transcript) -> String {
+        match &transcript.kind {
+            TranscriptKind::Contact(email) => self.display_name(email).to_string(),
+            TranscriptKind::Group(_) => format!(
+                "Group: {}",
+                transcript
+                    .participants
+                    .iter()
+                    .map(|participant| self.display_name(participant).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn display_name(&self, email: &Arc<String>) -> Arc<String> {
+        if *email == self.user_email {
+            self.user_display_name.clone()
+        } else if let Some(contact) = self.contact_repository.get_contact(email) {
+            contact.name().clone()
+        } else {
+            email.clone()
+        }
+    }
+
+    fn real_name(&self, email: &Arc<String>) -> Option<Arc<String>> {
+        self.contact_repository
+            .get_contact(email)
+            .and_then(|contact| contact.real_name().cloned())
+    }
+}
@@ -0,0 +1,189 @@
+use crate::contact_repository::ContactRepository;
+use crate::helpers::message_logs::{import_message_logs, read_message_logs};
+use crate::helpers::run_future::run_future;
+use crate::models::message_log::MessageLog;
+use crate::screens::contacts::contacts;
+use crate::sqlite::Sqlite;
+use eframe::egui;
+use msnp11_sdk::MsnpList;
+use rfd::AsyncFileDialog;
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+enum Message {
+    ReadResult(Option<(Vec<MessageLog>, Vec<String>)>),
+    ImportResult(Vec<String>),
+}
+
+pub struct ImportLogs {
+    logs: Vec<MessageLog>,
+    report: Vec<String>,
+    working: bool,
+    user_email: Arc<String>,
+    user_display_name: Arc<String>,
+    contact_repository: ContactRepository,
+    sqlite: Sqlite,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+    sender: mpsc::Sender<Message>,
+    receiver: mpsc::Receiver<Message>,
+}
+
+impl ImportLogs {
+    pub fn new(
+        user_email: Arc<String>,
+        user_display_name: Arc<String>,
+        contact_repository: ContactRepository,
+        sqlite: Sqlite,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+        handle: Handle,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            logs: Vec::new(),
+            report: Vec::new(),
+            working: false,
+            user_email,
+            user_display_name,
+            contact_repository,
+            sqlite,
+            contacts_sender,
+            handle,
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn import_logs(&mut self, ui: &mut egui::Ui) {
+        if let Ok(message) = self.receiver.try_recv() {
+            self.working = false;
+            match message {
+                Message::ReadResult(result) => {
+                    if let Some((logs, errors)) = result {
+                        self.logs = logs;
+                        self.report = errors;
+                    }
+                }
+
+                Message::ImportResult(report) => self.report = report,
+            }
+        }
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(15),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                ui.label(
+                    "Import the XML message logs saved by MSN Messenger and Windows Live Messenger.",
+                );
+
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!self.working, egui::Button::new("Choose Logs..."))
+                        .clicked()
+                    {
+                        self.choose_logs(ui.ctx().clone());
+                    }
+
+                    let can_import = !self.logs.is_empty()
+                        && self.logs.iter().all(|log| log.contact_email.contains('@'));
+
+                    if ui
+                        .add_enabled(!self.working && can_import, egui::Button::new("Import"))
+                        .on_disabled_hover_text("Every log needs a contact e-mail address")
+                        .clicked()
+                    {
+                        self.import(ui.ctx().clone());
+                    }
+
+                    if self.working {
+                        ui.spinner();
+                    }
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for line in &self.report {
+                            ui.label(line);
+                        }
+
+                        for log in &mut self.logs {
+                            ui.strong(format!("{} ({} messages)", log.file_name, log.entries.len()));
+                            ui.horizontal(|ui| {
+                                let label = ui.label("Contact e-mail:");
+                                ui.add(
+                                    egui::text_edit::TextEdit::singleline(&mut log.contact_email)
+                                        .hint_text("E-mail address"),
+                                )
+                                .labelled_by(label.id);
+                            });
+
+                            ui.label("Names you used in this log:");
+                            ui.indent(&log.file_name, |ui| {
+                                for friendly_name in &mut log.friendly_names {
+                                    ui.checkbox(&mut friendly_name.is_user, &friendly_name.name);
+                                }
+                            });
+
+                            ui.separator();
+                        }
+                    });
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::CloseImportLogs);
+        }
+    }
+
+    fn choose_logs(&mut self, ctx: egui::Context) {
+        let files = AsyncFileDialog::new()
+            .add_filter("Message logs", &["xml"])
+            .set_title("Select message logs")
+            .pick_files();
+
+        let contacts = self
+            .contact_repository
+            .get_contacts_in_list(MsnpList::ForwardList)
+            .unwrap_or_default();
+
+        let user_display_name = self.user_display_name.clone();
+        self.working = true;
+        run_future(
+            self.handle.clone(),
+            async move {
+                let result = read_message_logs(files, contacts, user_display_name).await;
+                ctx.request_repaint();
+                result
+            },
+            self.sender.clone(),
+            Message::ReadResult,
+        );
+    }
+
+    fn import(&mut self, ctx: egui::Context) {
+        let logs = std::mem::take(&mut self.logs);
+        let sqlite = self.sqlite.clone();
+        let user_email = self.user_email.clone();
+
+        self.working = true;
+        run_future(
+            self.handle.clone(),
+            async move {
+                let report = import_message_logs(&sqlite, &user_email, &logs);
+                ctx.request_repaint();
+                report
+            },
+            self.sender.clone(),
+            Message::ImportResult,
+        );
+    }
+}
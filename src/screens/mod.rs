@@ -2,11 +2,22 @@ use regex::Regex;
 use std::sync::LazyLock;
 
 mod add_contact;
+mod added_by;
+mod contact_alerts;
+mod contact_alias;
+mod contact_properties;
 pub mod contacts;
 pub mod conversation;
+mod group_name;
+mod history;
+mod import_logs;
 mod invite;
+pub mod offline_contacts;
 pub mod personal_settings;
+mod rename_contact;
+mod search;
 pub mod sign_in;
+pub mod unlock_database;
 
 pub static PLUS_TAGS_REGEX: LazyLock<Option<Regex>> =
     LazyLock::new(|| Regex::new(r"\[/?[abcius]=.*?]|\[/?[abcius]]").ok());
@@ -0,0 +1,274 @@
+use crate::contact_repository::ContactRepository;
+use crate::helpers::export_history::{export_history, pick_export_file};
+use crate::helpers::run_future::run_future;
+use crate::models::contact::Contact;
+use crate::screens::contacts::contacts;
+use crate::screens::history;
+use crate::sqlite::Sqlite;
+use crate::{main_window, svg};
+use eframe::egui;
+use eframe::egui::text::LayoutJob;
+use eframe::egui::{FontId, TextFormat};
+use msnp11_sdk::MsnpList;
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+// Lets the user browse the cached contact list and their history without signing in
+pub struct OfflineContacts {
+    user_email: Arc<String>,
+    contacts: Vec<Contact>,
+    selected_contact: Option<Arc<String>>,
+    contact_repository: ContactRepository,
+    history_window: Option<history::History>,
+    main_window_sender: mpsc::Sender<main_window::Message>,
+    sqlite: Sqlite,
+    handle: Handle,
+    sender: mpsc::Sender<contacts::Message>,
+    receiver: mpsc::Receiver<contacts::Message>,
+}
+
+impl OfflineContacts {
+    pub fn new(
+        user_email: Arc<String>,
+        main_window_sender: mpsc::Sender<main_window::Message>,
+        sqlite: Sqlite,
+        handle: Handle,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let contact_repository =
+            ContactRepository::with_cache(sqlite.clone(), user_email.clone(), handle.clone());
+
+        let mut contacts = contact_repository
+            .get_contacts_in_list(MsnpList::ForwardList)
+            .unwrap_or_default();
+
+        contacts.sort_by_key(|contact| contact.name().to_lowercase());
+
+        Self {
+            user_email,
+            contacts,
+            selected_contact: None,
+            contact_repository,
+            history_window: None,
+            main_window_sender,
+            sqlite,
+            handle,
+            sender,
+            receiver,
+        }
+    }
+
+    fn open_history(&mut self, ctx: &egui::Context) {
+        if self.history_window.is_some() {
+            ctx.send_viewport_cmd_to(
+                egui::ViewportId::from_hash_of("history"),
+                egui::ViewportCommand::Focus,
+            );
+        } else {
+            self.history_window = Some(history::History::new(
+                self.user_email.clone(),
+                self.user_email.clone(),
+                self.contact_repository.clone(),
+                self.sqlite.clone(),
+                self.sender.clone(),
+                self.handle.clone(),
+                ctx.clone(),
+            ));
+        }
+    }
+}
+
+impl eframe::App for OfflineContacts {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        if let Ok(message) = self.receiver.try_recv() {
+            match message {
+                contacts::Message::CloseHistory => self.history_window = None,
+                contacts::Message::ExportHistory(contact) => {
+                    let file_name = match &contact {
+                        Some(contact) => format!("{contact} history.html"),
+                        None => "meowsn history.html".to_string(),
+                    };
+
+                    run_future(
+                        self.handle.clone(),
+                        export_history(
+                            pick_export_file(&file_name),
+                            self.sqlite.clone(),
+                            self.contact_repository.clone(),
+                            self.user_email.clone(),
+                            self.user_email.clone(),
+                            contact,
+                        ),
+                        self.sender.clone(),
+                        contacts::Message::ExportHistoryResult,
+                    );
+                }
+
+                contacts::Message::ExportHistoryResult(result) => {
+                    let text = match result {
+                        Ok(Some(message_count)) => {
+                            Some(format!("Exported {message_count} messages"))
+                        }
+
+                        Ok(None) => None,
+                        Err(error) => Some(format!("Could not export history: {error}")),
+                    };
+
+                    if let Some(text) = text {
+                        let _ = self
+                            .main_window_sender
+                            .send(main_window::Message::OpenDialog(text));
+
+                        ui.request_repaint();
+                    }
+                }
+
+                _ => (),
+            }
+        }
+
+        egui::Panel::top("offline_user_info")
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(15),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.label(self.user_email.as_str());
+                        ui.weak("Browsing offline");
+                    });
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .button("Sign In")
+                            .on_hover_text("Go back to the sign in screen")
+                            .clicked()
+                        {
+                            let _ = self.main_window_sender.send(main_window::Message::SignOut);
+                            ui.request_repaint();
+                        }
+                    });
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(2.);
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .link("Message History")
+                        .on_hover_text("Browse your conversation history")
+                        .clicked()
+                    {
+                        self.open_history(ui.ctx());
+                    }
+                });
+            });
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin {
+                    top: 0,
+                    bottom: 15,
+                    left: 15,
+                    right: 15,
+                },
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        if self.contacts.is_empty() {
+                            ui.label("No contacts were saved for this account yet");
+                        }
+
+                        let mut open_history = false;
+                        for contact in &self.contacts {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::Image::new(
+                                        if contact.lists.contains(&MsnpList::BlockList) {
+                                            svg::default_display_picture_offline_blocked()
+                                        } else {
+                                            svg::default_display_picture_offline()
+                                        },
+                                    )
+                                    .fit_to_exact_size(egui::Vec2::splat(25.))
+                                    .alt_text("Contact status is unknown"),
+                                );
+
+                                let mut contact_job = LayoutJob::default();
+                                contact_job.append(
+                                    contact.name(),
+                                    0.,
+                                    TextFormat {
+                                        font_id: FontId::proportional(14.),
+                                        color: ui.visuals().text_color(),
+                                        ..Default::default()
+                                    },
+                                );
+
+                                if let Some(personal_message) = contact.personal_message.as_ref()
+                                    && !personal_message.is_empty()
+                                {
+                                    contact_job.append(
+                                        &format!(" - {personal_message}"),
+                                        0.,
+                                        TextFormat {
+                                            font_id: FontId::proportional(14.),
+                                            color: ui.visuals().weak_text_color(),
+                                            ..Default::default()
+                                        },
+                                    );
+                                }
+
+                                ui.style_mut().spacing.button_padding = egui::Vec2::new(5., 3.);
+                                let label = ui
+                                    .add(
+                                        egui::Button::selectable(
+                                            self.selected_contact
+                                                .as_ref()
+                                                .is_some_and(|selected| *selected == contact.email),
+                                            contact_job,
+                                        )
+                                        .truncate(),
+                                    )
+                                    .on_hover_text(format!(
+                                        "<{}>\nDouble click to open your message history.",
+                                        contact.email
+                                    ));
+
+                                if label.clicked() {
+                                    self.selected_contact = Some(contact.email.clone());
+                                }
+
+                                if label.double_clicked() {
+                                    open_history = true;
+                                }
+                            });
+                        }
+
+                        if open_history {
+                            self.open_history(ui.ctx());
+                        }
+                    });
+            });
+
+        if let Some(history) = &mut self.history_window {
+            ui.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("history"),
+                egui::ViewportBuilder::default()
+                    .with_title("Message History")
+                    .with_inner_size([700., 550.])
+                    .with_min_inner_size([500., 350.]),
+                |ui, _| {
+                    history.history(ui);
+                },
+            );
+        }
+    }
+}
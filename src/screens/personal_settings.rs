@@ -1,12 +1,15 @@
 use crate::contact_repository::ContactRepository;
+use crate::helpers::clean_display_pictures::{clean_display_pictures, format_size};
 use crate::helpers::run_future::run_future;
 use crate::screens::contacts::contacts;
-use crate::settings::Settings;
+use crate::settings::{HistoryRetention, Settings};
+use crate::sqlite::Sqlite;
 use crate::{main_window, settings};
 use eframe::egui;
 use egui_taffy::taffy::prelude::{auto, length, percent};
 use egui_taffy::{TuiBuilderLogic, taffy, tui};
 use msnp11_sdk::{Client, MsnpList};
+use std::collections::BTreeMap;
 use std::sync::{Arc, mpsc};
 use tokio::runtime::Handle;
 
@@ -14,6 +17,7 @@ use tokio::runtime::Handle;
 enum SelectedTab {
     General,
     Privacy,
+    History,
 }
 
 pub struct PersonalSettings {
@@ -24,8 +28,13 @@ pub struct PersonalSettings {
     check_for_updates: bool,
     notify_sign_ins: bool,
     notify_added_by: bool,
+    history_retention: HistoryRetention,
+    history_retention_overrides: BTreeMap<String, HistoryRetention>,
+    encrypt_history: bool,
+    override_contact: Option<Arc<String>>,
     only_in_contact_list: bool,
     client: Option<Arc<Client>>,
+    sqlite: Option<Sqlite>,
     main_window_sender: mpsc::Sender<main_window::Message>,
     contacts_sender: Option<mpsc::Sender<contacts::Message>>,
     handle: Handle,
@@ -35,10 +44,12 @@ pub struct PersonalSettings {
 }
 
 impl PersonalSettings {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         display_name: Option<String>,
         client: Option<Arc<Client>>,
         contact_repository: Option<ContactRepository>,
+        sqlite: Option<Sqlite>,
         main_window_sender: mpsc::Sender<main_window::Message>,
         contacts_sender: Option<mpsc::Sender<contacts::Message>>,
         blp_bl: Option<bool>,
@@ -53,8 +64,13 @@ impl PersonalSettings {
             check_for_updates: settings.check_for_updates,
             notify_sign_ins: settings.notify_sign_ins,
             notify_added_by: settings.notify_added_by,
+            history_retention: settings.history_retention,
+            history_retention_overrides: settings.history_retention_overrides,
+            encrypt_history: settings.encrypt_history,
+            override_contact: None,
             only_in_contact_list: blp_bl.unwrap_or_default(),
             client,
+            sqlite,
             main_window_sender,
             contacts_sender,
             handle,
@@ -86,6 +102,15 @@ impl PersonalSettings {
                     if label.clicked() || label.secondary_clicked() {
                         self.selected_tab = SelectedTab::Privacy;
                     }
+
+                    let label = ui.selectable_label(
+                        self.selected_tab == SelectedTab::History,
+                        "History      ",
+                    );
+
+                    if label.clicked() || label.secondary_clicked() {
+                        self.selected_tab = SelectedTab::History;
+                    }
                 })
             });
 
@@ -216,6 +241,12 @@ impl PersonalSettings {
                                             check_for_updates: self.check_for_updates,
                                             notify_sign_ins: self.notify_sign_ins,
                                             notify_added_by: self.notify_added_by,
+                                            history_retention: self.history_retention,
+                                            history_retention_overrides: self
+                                                .history_retention_overrides
+                                                .clone(),
+                                            encrypt_history: self.encrypt_history,
+                                            ..settings::get_settings().unwrap_or_default()
                                         };
 
                                         let _ = settings::save_settings(&settings);
@@ -433,6 +464,12 @@ impl PersonalSettings {
                                                 check_for_updates: self.check_for_updates,
                                                 notify_sign_ins: self.notify_sign_ins,
                                                 notify_added_by: self.notify_added_by,
+                                                history_retention: self.history_retention,
+                                                history_retention_overrides: self
+                                                    .history_retention_overrides
+                                                    .clone(),
+                                                encrypt_history: self.encrypt_history,
+                                                ..settings::get_settings().unwrap_or_default()
                                             };
 
                                             let _ = settings::save_settings(&settings);
@@ -467,8 +504,199 @@ impl PersonalSettings {
                                     });
                                 });
                         }
+
+                        SelectedTab::History => {
+                            tui.ui(|ui| {
+                                ui.label("Message history:");
+                                ui.add_space(3.);
+                                retention_selector(ui, "history-retention", &mut self.history_retention);
+                            });
+
+                            tui.ui(|ui| {
+                                ui.label("Contacts with their own history settings:");
+                                ui.add_space(3.);
+
+                                let mut removed_override = None;
+                                for (email, retention) in &mut self.history_retention_overrides {
+                                    ui.horizontal(|ui| {
+                                        ui.label(email);
+                                        retention_selector(ui, email, retention);
+                                        if ui.button("Remove").clicked() {
+                                            removed_override = Some(email.clone());
+                                        }
+                                    });
+                                }
+
+                                if let Some(email) = removed_override {
+                                    self.history_retention_overrides.remove(&email);
+                                }
+
+                                if let Some(contact_repository) = &self.contact_repository {
+                                    let mut contacts = contact_repository
+                                        .get_contacts_in_list(MsnpList::ForwardList)
+                                        .unwrap_or_default();
+
+                                    contacts.retain(|contact| {
+                                        !self.history_retention_overrides.contains_key(contact.email.as_str())
+                                    });
+
+                                    contacts.sort_by_key(|contact| contact.email.clone());
+                                    ui.horizontal(|ui| {
+                                        egui::ComboBox::from_id_salt("history-override-contact")
+                                            .selected_text(
+                                                self.override_contact
+                                                    .as_ref()
+                                                    .map(|email| email.as_str())
+                                                    .unwrap_or("Choose a contact"),
+                                            )
+                                            .show_ui(ui, |ui| {
+                                                for contact in contacts {
+                                                    ui.selectable_value(
+                                                        &mut self.override_contact,
+                                                        Some(contact.email.clone()),
+                                                        &*contact.email,
+                                                    );
+                                                }
+                                            });
+
+                                        if ui
+                                            .add_enabled(self.override_contact.is_some(), egui::Button::new("Add"))
+                                            .clicked()
+                                            && let Some(email) = self.override_contact.take()
+                                        {
+                                            self.history_retention_overrides
+                                                .insert(email.to_string(), self.history_retention);
+                                        }
+                                    });
+                                }
+                            });
+
+                            tui.ui(|ui| {
+                                ui.weak("Older messages are deleted when you sign in and every few hours afterwards.");
+                            });
+
+                            tui.ui(|ui| {
+                                ui.checkbox(&mut self.encrypt_history, "Encrypt message history");
+                                ui.weak("Takes effect the next time meowsn starts.");
+                            });
+
+                            if let Some(sqlite) = &self.sqlite {
+                                tui.ui(|ui| {
+                                    if ui
+                                        .button("Clean Up Display Pictures")
+                                        .on_hover_text("Delete display pictures no one has used in a while")
+                                        .clicked()
+                                    {
+                                        let sqlite = sqlite.clone();
+                                        run_future(
+                                            self.handle.clone(),
+                                            async move { clean_display_pictures(&sqlite) },
+                                            self.main_window_sender.clone(),
+                                            |result| main_window::Message::OpenDialog(match result {
+                                                Ok((deleted, reclaimed)) => format!(
+                                                    "Deleted {deleted} display pictures, freeing {}",
+                                                    format_size(reclaimed)
+                                                ),
+
+                                                Err(error) => {
+                                                    format!("Error cleaning up display pictures: {error}")
+                                                }
+                                            }),
+                                        );
+                                    }
+                                });
+                            }
+
+                            tui.style(taffy::Style {
+                                align_self: Some(taffy::AlignItems::Center),
+                                ..Default::default()
+                            })
+                            .ui(|ui| {
+                                ui.style_mut().spacing.button_padding = egui::Vec2::new(8., 5.);
+                                ui.horizontal(|ui| {
+                                    if ui.button("Save").on_hover_text("Save settings").clicked() {
+                                        let settings = Settings {
+                                            server: self.server.clone(),
+                                            nexus_url: self.nexus_url.clone(),
+                                            config_server: self.config_server.clone(),
+                                            check_for_updates: self.check_for_updates,
+                                            notify_sign_ins: self.notify_sign_ins,
+                                            notify_added_by: self.notify_added_by,
+                                            history_retention: self.history_retention,
+                                            history_retention_overrides: self
+                                                .history_retention_overrides
+                                                .clone(),
+                                            encrypt_history: self.encrypt_history,
+                                            ..settings::get_settings().unwrap_or_default()
+                                        };
+
+                                        let _ = settings::save_settings(&settings);
+                                        ui.send_viewport_cmd(egui::ViewportCommand::Close);
+                                    }
+
+                                    if ui
+                                        .button("Restore Defaults")
+                                        .on_hover_text("Restore default settings")
+                                        .clicked()
+                                    {
+                                        let defaults = Settings::default();
+                                        self.history_retention = defaults.history_retention;
+                                        self.history_retention_overrides =
+                                            defaults.history_retention_overrides;
+                                        self.encrypt_history = defaults.encrypt_history;
+                                    }
+                                });
+                            });
+                        }
                     }
                 });
         });
     }
 }
+
+fn retention_selector(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    retention: &mut HistoryRetention,
+) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(match retention {
+                HistoryRetention::Everything => "Keep everything",
+                HistoryRetention::Days(_) => "Keep messages for",
+                HistoryRetention::Messages(_) => "Keep the last",
+            })
+            .show_ui(ui, |ui| {
+                let days = match retention {
+                    HistoryRetention::Days(days) => *days,
+                    _ => 30,
+                };
+
+                let messages = match retention {
+                    HistoryRetention::Messages(messages) => *messages,
+                    _ => 1000,
+                };
+
+                ui.selectable_value(retention, HistoryRetention::Everything, "Keep everything");
+                ui.selectable_value(retention, HistoryRetention::Days(days), "Keep messages for");
+                ui.selectable_value(
+                    retention,
+                    HistoryRetention::Messages(messages),
+                    "Keep the last",
+                );
+            });
+
+        match retention {
+            HistoryRetention::Everything => (),
+            HistoryRetention::Days(days) => {
+                ui.add(egui::DragValue::new(days).range(1..=36500));
+                ui.label("days");
+            }
+
+            HistoryRetention::Messages(messages) => {
+                ui.add(egui::DragValue::new(messages).range(1..=1_000_000));
+                ui.label("messages per conversation");
+            }
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use crate::helpers::run_future::run_future;
+use crate::models::contact::Contact;
+use crate::screens::contacts::contacts;
+use eframe::egui;
+use egui_taffy::taffy::prelude::{auto, length, percent};
+use egui_taffy::{TuiBuilderLogic, taffy, tui};
+use msnp11_sdk::Client;
+use std::sync::{Arc, mpsc};
+use tokio::runtime::Handle;
+
+// Changes the name the server keeps for a contact in the forward list
+pub struct RenameContact {
+    name: String,
+    contact: Contact,
+    client: Arc<Client>,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+    handle: Handle,
+}
+
+impl RenameContact {
+    pub fn new(
+        contact: Contact,
+        client: Arc<Client>,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+        handle: Handle,
+    ) -> Self {
+        Self {
+            name: contact
+                .friendly_name
+                .as_ref()
+                .unwrap_or(&contact.display_name)
+                .to_string(),
+            contact,
+            client,
+            contacts_sender,
+            handle,
+        }
+    }
+
+    pub fn rename_contact(&mut self, ui: &mut egui::Ui) {
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame {
+                    fill: ui.visuals().window_fill,
+                    ..Default::default()
+                }
+                .inner_margin(5.),
+            )
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("rename-contact-screen"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
+                        align_items: Some(taffy::AlignItems::Stretch),
+                        size: taffy::Size {
+                            width: percent(0.9),
+                            height: auto(),
+                        },
+                        padding: length(20.),
+                        gap: length(15.),
+                        ..Default::default()
+                    })
+                    .show(|tui| {
+                        tui.ui(|ui| {
+                            let label = ui.label(format!("New name for <{}>:", self.contact.email));
+
+                            ui.add_space(3.);
+                            ui.add(
+                                egui::text_edit::TextEdit::singleline(&mut self.name)
+                                    .hint_text("Name")
+                                    .min_size(egui::Vec2::new(340., 5.)),
+                            )
+                            .labelled_by(label.id);
+                        });
+
+                        tui.style(taffy::Style {
+                            align_self: Some(taffy::AlignItems::Center),
+                            size: taffy::Size {
+                                width: percent(0.2),
+                                height: auto(),
+                            },
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Ok").clicked() {
+                                    let name = Arc::new(self.name.trim().to_string());
+                                    if !name.is_empty()
+                                        && Some(&name) != self.contact.friendly_name.as_ref()
+                                        && let Some(guid) = self.contact.guid.clone()
+                                    {
+                                        let client = self.client.clone();
+                                        let email = self.contact.email.clone();
+                                        let new_name = name.clone();
+
+                                        run_future(
+                                            self.handle.clone(),
+                                            async move {
+                                                client
+                                                    .set_contact_display_name(&guid, &new_name)
+                                                    .await
+                                            },
+                                            self.contacts_sender.clone(),
+                                            move |result| contacts::Message::RenameContactResult {
+                                                email: email.clone(),
+                                                name: name.clone(),
+                                                result,
+                                            },
+                                        );
+                                    }
+
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseRenameContact);
+                                }
+
+                                if ui.button("Cancel").clicked() {
+                                    let _ = self
+                                        .contacts_sender
+                                        .send(contacts::Message::CloseRenameContact);
+                                }
+                            })
+                        })
+                    })
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::CloseRenameContact);
+        }
+    }
+}
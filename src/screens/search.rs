@@ -0,0 +1,309 @@
+use crate::contact_repository::ContactRepository;
+use crate::models::search_result::SearchResult;
+use crate::screens::contacts::contacts;
+use crate::sqlite::Sqlite;
+use chrono::{Local, NaiveDate, TimeZone};
+use eframe::egui;
+use eframe::egui::text::LayoutJob;
+use eframe::egui::{FontSelection, TextFormat};
+use msnp11_sdk::MsnpList;
+use std::sync::{Arc, mpsc};
+
+const RESULT_LIMIT: u32 = 200;
+
+pub struct Search {
+    query: String,
+    contact: Option<Arc<String>>,
+    from_date: String,
+    to_date: String,
+    results: Vec<SearchResult>,
+    searched: bool,
+    error: Option<String>,
+    user_email: Arc<String>,
+    contact_repository: ContactRepository,
+    sqlite: Sqlite,
+    contacts_sender: mpsc::Sender<contacts::Message>,
+}
+
+impl Search {
+    pub fn new(
+        user_email: Arc<String>,
+        contact_repository: ContactRepository,
+        sqlite: Sqlite,
+        contacts_sender: mpsc::Sender<contacts::Message>,
+    ) -> Self {
+        Self {
+            query: String::default(),
+            contact: None,
+            from_date: String::default(),
+            to_date: String::default(),
+            results: Vec::new(),
+            searched: false,
+            error: None,
+            user_email,
+            contact_repository,
+            sqlite,
+            contacts_sender,
+        }
+    }
+
+    pub fn search(&mut self, ui: &mut egui::Ui) {
+        egui::Panel::top("search_filters")
+            .frame(egui::Frame {
+                inner_margin: egui::Margin::same(15),
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                let mut search = false;
+                ui.horizontal(|ui| {
+                    let query = ui.add(
+                        egui::text_edit::TextEdit::singleline(&mut self.query)
+                            .hint_text("Search your conversation history")
+                            .desired_width(ui.available_width() - 70.),
+                    );
+
+                    search |= query.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    search |= ui.button("Search").clicked();
+                });
+
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    let contacts = self.contact_email_options();
+                    let label = ui.label("Contact:");
+                    egui::ComboBox::from_id_salt("search-contact")
+                        .selected_text(
+                            self.contact
+                                .as_ref()
+                                .map(|email| email.as_str())
+                                .unwrap_or("All contacts"),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.contact, None, "All contacts");
+                            for (email, display_name) in contacts {
+                                ui.selectable_value(
+                                    &mut self.contact,
+                                    Some(email.clone()),
+                                    format!("{display_name} <{email}>"),
+                                );
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+
+                    ui.add_space(10.);
+                    let label = ui.label("From:");
+                    ui.add(
+                        egui::text_edit::TextEdit::singleline(&mut self.from_date)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(85.),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Only show messages sent on or after this day");
+
+                    let label = ui.label("To:");
+                    ui.add(
+                        egui::text_edit::TextEdit::singleline(&mut self.to_date)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(85.),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Only show messages sent on or before this day");
+                });
+
+                if search {
+                    self.run_search();
+                }
+
+                if let Some(error) = &self.error {
+                    ui.add_space(5.);
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                inner_margin: egui::Margin {
+                    top: 0,
+                    bottom: 15,
+                    left: 15,
+                    right: 15,
+                },
+                fill: ui.visuals().window_fill,
+                ..Default::default()
+            })
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        if self.searched && self.results.is_empty() {
+                            ui.label("No messages found");
+                        }
+
+                        for result in &self.results {
+                            self.search_result(ui, result);
+                            ui.separator();
+                        }
+                    });
+            });
+
+        if ui.input(|i| i.viewport().close_requested()) {
+            let _ = self.contacts_sender.send(contacts::Message::CloseSearch);
+        }
+    }
+
+    fn run_search(&mut self) {
+        self.error = None;
+        let from_timestamp = match parse_date(&self.from_date) {
+            Ok(date) => date.and_then(start_of_day),
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+
+        let to_timestamp = match parse_date(&self.to_date) {
+            Ok(date) => date.and_then(|date| date.succ_opt()).and_then(start_of_day),
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+
+        match self.sqlite.search_messages(
+            &self.user_email,
+            &self.query,
+            self.contact.as_ref().map(|email| email.as_str()),
+            from_timestamp,
+            to_timestamp,
+            RESULT_LIMIT,
+        ) {
+            Ok(results) => {
+                self.results = results;
+                self.searched = true;
+            }
+
+            Err(error) => self.error = Some(format!("Could not search messages: {error}")),
+        }
+    }
+
+    fn search_result(&self, ui: &mut egui::Ui, result: &SearchResult) {
+        let message = &result.message;
+        let receiver = match &message.receiver {
+            Some(receiver) => self.display_name(receiver),
+            None => "a group conversation".to_string(),
+        };
+
+        let date = Some(message.timestamp)
+            .filter(|timestamp| *timestamp != 0)
+            .and_then(|timestamp| Local.timestamp_opt(timestamp, 0).single())
+            .map(|date_time| date_time.format("%B %-d, %Y %H:%M").to_string())
+            .unwrap_or_else(|| "Unknown date".to_string());
+
+        ui.horizontal(|ui| {
+            ui.weak(format!(
+                "{} to {receiver}",
+                self.display_name(&message.sender)
+            ));
+
+            ui.weak(format!(" - {date}"));
+        });
+
+        let mut job = LayoutJob::default();
+        let font_id = FontSelection::Default.resolve(ui.style());
+        for (index, part) in result.snippet.split('\u{2}').enumerate() {
+            let (highlighted, rest) = if index == 0 {
+                ("", part)
+            } else {
+                part.split_once('\u{3}').unwrap_or((part, ""))
+            };
+
+            job.append(
+                highlighted,
+                0.,
+                TextFormat {
+                    font_id: font_id.clone(),
+                    color: ui.visuals().strong_text_color(),
+                    background: ui.visuals().selection.bg_fill,
+                    ..Default::default()
+                },
+            );
+
+            job.append(
+                rest,
+                0.,
+                TextFormat {
+                    font_id: font_id.clone(),
+                    color: ui.visuals().text_color(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        job.wrap.max_width = ui.available_width();
+        ui.label(job);
+
+        // Group messages sent by the user can't be traced back to a single contact
+        let contact = if message.sender == self.user_email {
+            message.receiver.clone()
+        } else {
+            Some(message.sender.clone())
+        };
+
+        if let Some(contact) = contact
+            && let Some(message_id) = message.id
+            && ui
+                .link("Open conversation")
+                .on_hover_text("Open this conversation at this message")
+                .clicked()
+        {
+            let _ = self
+                .contacts_sender
+                .send(contacts::Message::OpenSearchResult {
+                    email: contact,
+                    message_id,
+                });
+        }
+    }
+
+    fn display_name(&self, email: &Arc<String>) -> String {
+        if *email == self.user_email {
+            "You".to_string()
+        } else if let Some(contact) = self.contact_repository.get_contact(email) {
+            contact.name().to_string()
+        } else {
+            email.to_string()
+        }
+    }
+
+    fn contact_email_options(&self) -> Vec<(Arc<String>, Arc<String>)> {
+        let mut contacts: Vec<_> = self
+            .contact_repository
+            .get_contacts_in_list(MsnpList::ForwardList)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|contact| (contact.email.clone(), contact.name().clone()))
+            .collect();
+
+        contacts.sort_by_key(|(_, display_name)| display_name.to_lowercase());
+        contacts
+    }
+}
+
+fn parse_date(date: &str) -> Result<Option<NaiveDate>, String> {
+    if date.trim().is_empty() {
+        return Ok(None);
+    }
+
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| "Dates must be in the YYYY-MM-DD format".to_string())
+}
+
+fn start_of_day(date: NaiveDate) -> Option<i64> {
+    date.and_hms_opt(0, 0, 0)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|date_time| date_time.timestamp())
+}
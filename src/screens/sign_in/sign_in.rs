@@ -329,6 +329,24 @@ impl eframe::App for SignIn {
                                 {
                                     self.remember_me = true;
                                 }
+
+                                // Only remembered accounts have a saved contact list
+                                if self.emails.contains(&self.email)
+                                    && ui
+                                        .link("Browse Offline")
+                                        .on_hover_text(
+                                            "View your contacts and history without signing in",
+                                        )
+                                        .clicked()
+                                {
+                                    let _ = self.main_window_sender.send(
+                                        main_window::Message::BrowseOffline(Arc::new(
+                                            self.email.clone(),
+                                        )),
+                                    );
+
+                                    ui.request_repaint();
+                                }
                             })
                         });
 
@@ -0,0 +1,257 @@
+use crate::helpers::database_key::{create_database_key, delete_database_key, get_database_key};
+use crate::helpers::run_future::run_future;
+use crate::main_window;
+use crate::settings;
+use crate::sqlite::{Sqlite, database_is_encrypted, is_wrong_key};
+use eframe::egui;
+use egui_taffy::taffy::prelude::{auto, length, percent};
+use egui_taffy::{TuiBuilderLogic, taffy, tui};
+use std::sync::mpsc;
+use tokio::runtime::Handle;
+
+pub enum Message {
+    UnlockResult(anyhow::Result<Sqlite>),
+}
+
+pub enum OpenedDatabase {
+    Opened(Sqlite),
+    // Holds why the key couldn't be kept in the keyring, if that's why a passphrase is needed
+    NeedsPassphrase(Option<String>),
+}
+
+pub struct UnlockDatabase {
+    passphrase: String,
+    confirm_passphrase: String,
+    choosing_passphrase: bool,
+    encrypt: bool,
+    unlocking: bool,
+    error: Option<String>,
+    keyring_error: Option<String>,
+    main_window_sender: mpsc::Sender<main_window::Message>,
+    handle: Handle,
+    sender: mpsc::Sender<Message>,
+    receiver: mpsc::Receiver<Message>,
+}
+
+// Opens the database with the key kept in the keyring, creating one when encrypting for the first
+// time. Only a missing or wrong key leaves the user to type a passphrase, other errors are returned.
+pub fn open_database() -> anyhow::Result<OpenedDatabase> {
+    let encrypt = settings::get_settings().unwrap_or_default().encrypt_history;
+    let encrypted = database_is_encrypted().unwrap_or_default();
+    if !encrypt && !encrypted {
+        return Sqlite::new(None, false).map(OpenedDatabase::Opened);
+    }
+
+    let key = match get_database_key() {
+        Some(key) => key,
+        None if encrypted => return Ok(OpenedDatabase::NeedsPassphrase(None)),
+        None => match create_database_key() {
+            Ok(key) => key,
+            Err(error) => return Ok(OpenedDatabase::NeedsPassphrase(Some(format!("{error:#}")))),
+        },
+    };
+
+    let sqlite = match Sqlite::new(Some(&key), encrypt) {
+        Ok(sqlite) => sqlite,
+        Err(error) if is_wrong_key(&error) => return Ok(OpenedDatabase::NeedsPassphrase(None)),
+        Err(error) => return Err(error),
+    };
+
+    if !encrypt {
+        delete_database_key();
+    }
+
+    Ok(OpenedDatabase::Opened(sqlite))
+}
+
+impl UnlockDatabase {
+    pub fn new(
+        keyring_error: Option<String>,
+        main_window_sender: mpsc::Sender<main_window::Message>,
+        handle: Handle,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            passphrase: String::default(),
+            confirm_passphrase: String::default(),
+            choosing_passphrase: !database_is_encrypted().unwrap_or_default(),
+            encrypt: settings::get_settings().unwrap_or_default().encrypt_history,
+            unlocking: false,
+            error: None,
+            keyring_error,
+            main_window_sender,
+            handle,
+            sender,
+            receiver,
+        }
+    }
+
+    fn unlock(&mut self) {
+        if self.passphrase.is_empty() {
+            self.error = Some("Please type a passphrase".to_string());
+            return;
+        }
+
+        if self.choosing_passphrase && self.passphrase != self.confirm_passphrase {
+            self.error = Some("The passphrases don't match".to_string());
+            return;
+        }
+
+        self.error = None;
+        self.unlocking = true;
+
+        let passphrase = self.passphrase.clone();
+        let encrypt = self.encrypt;
+        run_future(
+            self.handle.clone(),
+            async move { Sqlite::new(Some(&passphrase), encrypt) },
+            self.sender.clone(),
+            Message::UnlockResult,
+        );
+    }
+}
+
+impl eframe::App for UnlockDatabase {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        if let Ok(message) = self.receiver.try_recv() {
+            let Message::UnlockResult(result) = message;
+            self.unlocking = false;
+
+            match result {
+                Ok(sqlite) => {
+                    if !self.encrypt {
+                        delete_database_key();
+                    }
+
+                    let _ = self
+                        .main_window_sender
+                        .send(main_window::Message::DatabaseUnlocked(sqlite));
+                }
+
+                Err(error) => self.error = Some(format!("{error:#}")),
+            }
+
+            ui.request_repaint();
+        }
+
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame {
+                    fill: ui.visuals().window_fill,
+                    ..Default::default()
+                }
+                .inner_margin(30.),
+            )
+            .show_inside(ui, |ui| {
+                tui(ui, ui.id().with("unlock-database-screen"))
+                    .reserve_available_space()
+                    .style(taffy::Style {
+                        flex_direction: taffy::FlexDirection::Column,
+                        align_items: Some(taffy::AlignItems::Center),
+                        size: taffy::Size {
+                            width: percent(1.),
+                            height: auto(),
+                        },
+                        padding: length(8.),
+                        gap: length(15.),
+                        ..Default::default()
+                    })
+                    .show(|tui| {
+                        tui.style(taffy::Style {
+                            size: taffy::Size {
+                                width: length(250.),
+                                height: auto(),
+                            },
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            if !self.choosing_passphrase {
+                                ui.label(
+                                    "Your message history is encrypted. Type your passphrase to \
+                                    open it:",
+                                );
+                            } else if let Some(keyring_error) = &self.keyring_error {
+                                ui.label(
+                                    "The encryption key couldn't be saved in the system keyring, \
+                                    so your message history will be encrypted with a passphrase. \
+                                    Choose one below:",
+                                );
+
+                                ui.add_space(3.);
+                                ui.weak(keyring_error);
+                            } else {
+                                ui.label(
+                                    "Your message history will be encrypted with a passphrase. \
+                                    Choose one below:",
+                                );
+                            }
+                        });
+
+                        tui.style(taffy::Style {
+                            size: taffy::Size {
+                                width: length(250.),
+                                height: auto(),
+                            },
+                            ..Default::default()
+                        })
+                        .ui(|ui| {
+                            ui.add_enabled_ui(!self.unlocking, |ui| {
+                                ui.style_mut().spacing.item_spacing.y = 4.3;
+                                let label = ui.label("Passphrase:");
+                                let response = ui
+                                    .add(
+                                        egui::text_edit::TextEdit::singleline(&mut self.passphrase)
+                                            .hint_text("Passphrase")
+                                            .password(true),
+                                    )
+                                    .labelled_by(label.id);
+
+                                let mut submitted = response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                                if self.choosing_passphrase {
+                                    let label = ui.label("Confirm passphrase:");
+                                    let response = ui
+                                        .add(
+                                            egui::text_edit::TextEdit::singleline(
+                                                &mut self.confirm_passphrase,
+                                            )
+                                            .hint_text("Passphrase")
+                                            .password(true),
+                                        )
+                                        .labelled_by(label.id);
+
+                                    submitted = response.lost_focus()
+                                        && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                }
+
+                                if submitted {
+                                    self.unlock();
+                                }
+                            })
+                        });
+
+                        tui.ui(|ui| {
+                            if self.unlocking {
+                                ui.spinner();
+                            } else if ui
+                                .button(if self.choosing_passphrase {
+                                    "Encrypt"
+                                } else {
+                                    "Unlock"
+                                })
+                                .clicked()
+                            {
+                                self.unlock();
+                            }
+                        });
+
+                        if let Some(error) = &self.error {
+                            tui.ui(|ui| {
+                                ui.colored_label(ui.visuals().error_fg_color, error);
+                            });
+                        }
+                    });
+            });
+    }
+}
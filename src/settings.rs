@@ -1,5 +1,39 @@
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum HistoryRetention {
+    #[default]
+    Everything,
+    Days(u32),
+    Messages(u32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ContactSortOrder {
+    #[default]
+    DisplayName,
+    Status,
+    RecentActivity,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ContactGrouping {
+    #[default]
+    Groups,
+    Status,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct ContactListView {
+    pub sort_order: ContactSortOrder,
+    pub grouping: ContactGrouping,
+    pub hide_offline: bool,
+    pub hide_empty_groups: bool,
+    pub compact: bool,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Settings {
@@ -9,6 +43,14 @@ pub struct Settings {
     pub check_for_updates: bool,
     pub notify_sign_ins: bool,
     pub notify_added_by: bool,
+    #[serde(default)]
+    pub encrypt_history: bool,
+    #[serde(default)]
+    pub history_retention: HistoryRetention,
+    #[serde(default)]
+    pub history_retention_overrides: BTreeMap<String, HistoryRetention>,
+    #[serde(default)]
+    pub contact_list_view: ContactListView,
 }
 
 impl Default for Settings {
@@ -20,6 +62,10 @@ impl Default for Settings {
             check_for_updates: true,
             notify_sign_ins: true,
             notify_added_by: true,
+            encrypt_history: false,
+            history_retention: HistoryRetention::Everything,
+            history_retention_overrides: BTreeMap::new(),
+            contact_list_view: ContactListView::default(),
         }
     }
 }
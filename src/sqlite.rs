@@ -1,11 +1,22 @@
+use crate::migrations;
+use crate::models::alert_rule::AlertRule;
+use crate::models::contact::Contact;
 use crate::models::display_picture::DisplayPicture;
+use crate::models::history_stats::HistoryStats;
 use crate::models::message;
+use crate::models::outbox_entry::OutboxEntry;
+use crate::models::presence_entry::PresenceEntry;
+use crate::models::search_result::SearchResult;
+use crate::models::transcript::{Transcript, TranscriptKind};
 use crate::models::user::User;
 use anyhow::Context;
+use msnp11_sdk::{MsnpList, MsnpStatus};
 use r2d2::Pool;
 use r2d2_sqlite::rusqlite::fallible_streaming_iterator::FallibleStreamingIterator;
 use r2d2_sqlite::rusqlite::params;
 use r2d2_sqlite::{SqliteConnectionManager, rusqlite};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -14,74 +25,33 @@ pub struct Sqlite {
 }
 
 impl Sqlite {
-    pub fn new() -> anyhow::Result<Self> {
-        // Compatibility with previous name
-        let mut old_data_local =
-            dirs::data_local_dir().context("Could not find local data directory")?;
-
-        old_data_local.push("icedm");
-
-        let mut data_local =
-            dirs::data_local_dir().context("Could not find local data directory")?;
-
-        data_local.push("meowsn");
-
-        if old_data_local.exists() {
-            std::fs::rename(old_data_local.clone(), data_local.clone())?;
+    pub fn new(key: Option<&str>, encrypt: bool) -> anyhow::Result<Self> {
+        let data_local = database_path()?;
+        if data_local.exists() && database_is_encrypted()? != encrypt {
+            convert_database(&data_local, key, encrypt)?;
         }
 
-        std::fs::create_dir_all(&data_local)?;
-
-        let mut old_data_local = data_local.clone();
-        old_data_local.push("icedm");
-        old_data_local.set_extension("db");
+        let key = key.filter(|_| encrypt).map(|key| key.to_string());
+        if let Some(key) = &key {
+            // SQLCipher only notices a wrong key once something is read. Checking before the pool
+            // is built keeps it from retrying the bad key until it times out
+            let conn = rusqlite::Connection::open(&data_local)?;
+            conn.pragma_update(None, "key", key)?;
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+                .context("Could not open the database, the passphrase may be wrong")?;
+        }
 
-        data_local.push("meowsn");
-        data_local.set_extension("db");
+        let manager = SqliteConnectionManager::file(&data_local).with_init(move |conn| {
+            if let Some(key) = &key {
+                conn.pragma_update(None, "key", key)?;
+            }
 
-        if old_data_local.exists() {
-            std::fs::rename(old_data_local, data_local.clone())?;
-        }
+            Ok(())
+        });
 
-        let manager = SqliteConnectionManager::file(data_local);
         let pool = Pool::new(manager)?;
-        let conn = pool.get()?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS display_pictures (\
-                id INTEGER PRIMARY KEY,\
-                picture BLOB NOT NULL,\
-                hash TEXT UNIQUE NOT NULL\
-            )",
-            (),
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS users (\
-                id INTEGER PRIMARY KEY,\
-                email TEXT UNIQUE NOT NULL,\
-                personal_message TEXT,\
-                display_picture_id INTEGER,\
-                FOREIGN KEY (display_picture_id) REFERENCES display_pictures (id)\
-            )",
-            (),
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS messages (\
-                id INTEGER PRIMARY KEY,\
-                sender TEXT NOT NULL,\
-                receiver TEXT,\
-                is_nudge BOOL NOT NULL,\
-                text TEXT NOT NULL,\
-                bold BOOL NOT NULL,\
-                italic BOOL NOT NULL,\
-                underline BOOL NOT NULL,\
-                strikethrough BOOL NOT NULL,\
-                session_id TEXT\
-            )",
-            (),
-        )?;
+        let mut conn = pool.get()?;
+        migrations::run_migrations(&mut conn, &data_local)?;
 
         Ok(Self { pool })
     }
@@ -130,41 +100,86 @@ impl Sqlite {
         if let Ok(conn) = self.pool.get() {
             let mut stmt = conn.prepare("SELECT picture FROM display_pictures WHERE hash = ?1")?;
             let picture = stmt.query_map([hash], |row| row.get::<usize, Arc<[u8]>>(0))?;
-            return picture.last().ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+            let picture = picture
+                .last()
+                .ok_or(rusqlite::Error::QueryReturnedNoRows)??;
+
+            // Pictures still in use by contacts are kept by the cleanup
+            conn.execute(
+                "UPDATE display_pictures SET last_seen = ?1 WHERE hash = ?2",
+                params![chrono::Local::now().timestamp(), hash],
+            )?;
+
+            return Ok(picture);
         }
 
         Err(rusqlite::Error::QueryReturnedNoRows)
     }
 
-    pub fn select_messages(
+    // Selects the oldest messages newer than the given timestamp and id
+    pub fn select_messages_after(
         &self,
-        sender1: &str,
-        sender2: &str,
+        user_email: &str,
+        contact_email: &str,
+        after: (i64, i64),
         limit: u32,
     ) -> rusqlite::Result<Vec<message::Message>> {
         if let Ok(conn) = self.pool.get() {
             let mut stmt = conn.prepare(
-                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id FROM messages \
-                WHERE (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) ORDER BY id DESC LIMIT ?3",
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) \
+                AND (timestamp > ?3 OR timestamp = ?3 AND id > ?4) \
+                ORDER BY timestamp, id LIMIT ?5",
             )?;
 
-            let messages = stmt.query_map(params![sender1, sender2, limit], |row| {
-                Ok(message::Message {
-                    sender: Arc::new(row.get(0)?),
-                    receiver: row.get(1).ok().map(Arc::new),
-                    is_nudge: row.get(2)?,
-                    text: row.get(3)?,
-                    bold: row.get(4)?,
-                    italic: row.get(5)?,
-                    underline: row.get(6)?,
-                    strikethrough: row.get(7)?,
-                    session_id: row.get(8).ok().map(Arc::new),
-                    color: "0".to_string(),
-                    is_history: true,
-                    errored: false,
-                })
-            });
+            let messages = stmt.query_map(
+                params![user_email, contact_email, after.0, after.1, limit],
+                message_from_row,
+            );
+            return messages?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
 
+    pub fn select_message_timestamp(&self, user_email: &str, id: i64) -> rusqlite::Result<i64> {
+        if let Ok(conn) = self.pool.get() {
+            return conn.query_row(
+                "SELECT timestamp FROM messages WHERE owner = ?1 AND id = ?2",
+                params![user_email, id],
+                |row| row.get(0),
+            );
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Selects the newest messages, or the newest ones older than the given timestamp and id
+    pub fn select_messages(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+        before: Option<(i64, i64)>,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<message::Message>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) \
+                AND (?3 IS NULL OR timestamp < ?3 OR timestamp = ?3 AND id < ?4) \
+                ORDER BY timestamp DESC, id DESC LIMIT ?5",
+            )?;
+
+            let messages = stmt.query_map(
+                params![
+                    user_email,
+                    contact_email,
+                    before.map(|(timestamp, _)| timestamp),
+                    before.map(|(_, id)| id),
+                    limit
+                ],
+                message_from_row,
+            );
             return messages?.collect();
         }
 
@@ -173,67 +188,259 @@ impl Sqlite {
 
     pub fn select_all_messages(
         &self,
-        sender1: &str,
-        sender2: &str,
+        user_email: &str,
+        contact_email: &str,
     ) -> rusqlite::Result<Vec<message::Message>> {
         if let Ok(conn) = self.pool.get() {
             let mut stmt = conn.prepare(
-                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id FROM messages \
-                WHERE (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2)",
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) ORDER BY timestamp, id",
             )?;
 
-            let messages = stmt.query_map([sender1, sender2], |row| {
-                Ok(message::Message {
-                    sender: Arc::new(row.get(0)?),
-                    receiver: row.get(1).ok().map(Arc::new),
-                    is_nudge: row.get(2)?,
-                    text: row.get(3)?,
-                    bold: row.get(4)?,
-                    italic: row.get(5)?,
-                    underline: row.get(6)?,
-                    strikethrough: row.get(7)?,
-                    session_id: row.get(8).ok().map(Arc::new),
-                    color: "0".to_string(),
-                    is_history: true,
-                    errored: false,
-                })
-            });
-
+            let messages = stmt.query_map([user_email, contact_email], message_from_row);
             return messages?.collect();
         }
 
         Err(rusqlite::Error::QueryReturnedNoRows)
     }
 
-    pub fn select_messages_by_session_id(
+    // Same as select_messages, for the group conversation with the given participants key
+    pub fn select_group_messages(
         &self,
-        session_id: &str,
+        user_email: &str,
+        participants: &str,
+        before: Option<(i64, i64)>,
         limit: u32,
     ) -> rusqlite::Result<Vec<message::Message>> {
         if let Ok(conn) = self.pool.get() {
             let mut stmt = conn.prepare(
-                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id FROM messages \
-                WHERE session_id = ?1 ORDER BY id DESC LIMIT ?2",
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                WHERE owner = ?1 AND participants = ?2 \
+                AND (?3 IS NULL OR timestamp < ?3 OR timestamp = ?3 AND id < ?4) \
+                ORDER BY timestamp DESC, id DESC LIMIT ?5",
+            )?;
+
+            let messages = stmt.query_map(
+                params![
+                    user_email,
+                    participants,
+                    before.map(|(timestamp, _)| timestamp),
+                    before.map(|(_, id)| id),
+                    limit
+                ],
+                message_from_row,
+            );
+            return messages?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn select_transcripts(&self, user_email: &str) -> rusqlite::Result<Vec<Transcript>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT CASE WHEN sender = ?1 THEN receiver ELSE sender END AS contact, COUNT(*), MAX(timestamp) \
+                FROM messages WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND receiver IS NOT NULL AND participants IS NULL \
+                GROUP BY contact",
             )?;
 
-            let messages = stmt.query_map(params![session_id, limit], |row| {
-                Ok(message::Message {
-                    sender: Arc::new(row.get(0)?),
-                    receiver: row.get(1).ok().map(Arc::new),
-                    is_nudge: row.get(2)?,
-                    text: row.get(3)?,
-                    bold: row.get(4)?,
-                    italic: row.get(5)?,
-                    underline: row.get(6)?,
-                    strikethrough: row.get(7)?,
-                    session_id: row.get(8).ok().map(Arc::new),
-                    color: "0".to_string(),
-                    is_history: true,
-                    errored: false,
+            let mut transcripts = stmt
+                .query_map([user_email], |row| {
+                    let contact = Arc::new(row.get::<usize, String>(0)?);
+                    Ok(Transcript {
+                        kind: TranscriptKind::Contact(contact.clone()),
+                        participants: vec![contact],
+                        message_count: row.get(1)?,
+                        last_timestamp: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT participants, COUNT(*), MAX(timestamp) \
+                FROM messages WHERE owner = ?1 AND participants IS NOT NULL GROUP BY participants",
+            )?;
+
+            let groups = stmt.query_map([user_email], |row| {
+                let participants: String = row.get(0)?;
+                Ok(Transcript {
+                    participants: participants
+                        .split(',')
+                        .filter(|participant| !participant.is_empty())
+                        .map(|participant| Arc::new(participant.to_string()))
+                        .collect(),
+                    kind: TranscriptKind::Group(Arc::new(participants)),
+                    message_count: row.get(1)?,
+                    last_timestamp: row.get(2)?,
                 })
-            });
+            })?;
 
-            return messages?.collect();
+            for group in groups {
+                transcripts.push(group?);
+            }
+
+            transcripts.sort_by_key(|transcript| std::cmp::Reverse(transcript.last_timestamp));
+            return Ok(transcripts);
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Returns when the user last exchanged a message with each contact
+    pub fn select_last_activity(&self, user_email: &str) -> rusqlite::Result<Vec<(String, i64)>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT CASE WHEN sender = ?1 THEN receiver ELSE sender END AS contact, MAX(timestamp) \
+                FROM messages WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND receiver IS NOT NULL \
+                GROUP BY contact",
+            )?;
+
+            let activity = stmt.query_map([user_email], |row| Ok((row.get(0)?, row.get(1)?)));
+            return activity?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Counts the messages exchanged with a contact, group messages they sent are counted apart
+    pub fn select_history_stats(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+    ) -> rusqlite::Result<HistoryStats> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT COUNT(*), COALESCE(SUM(sender = ?1), 0), COALESCE(SUM(is_nudge), 0), MIN(NULLIF(timestamp, 0)), MAX(NULLIF(timestamp, 0)) FROM messages \
+                WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) AND participants IS NULL",
+            )?;
+
+            let mut stats = stmt.query_row([user_email, contact_email], |row| {
+                Ok(HistoryStats {
+                    message_count: row.get(0)?,
+                    sent_count: row.get(1)?,
+                    nudge_count: row.get(2)?,
+                    group_message_count: 0,
+                    first_timestamp: row.get(3)?,
+                    last_timestamp: row.get(4)?,
+                })
+            })?;
+
+            stats.group_message_count = conn.query_row(
+                "SELECT COUNT(*) FROM messages WHERE owner = ?1 AND participants IS NOT NULL AND sender = ?2",
+                [user_email, contact_email],
+                |row| row.get(0),
+            )?;
+
+            return Ok(stats);
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn select_transcript_page(
+        &self,
+        user_email: &str,
+        kind: &TranscriptKind,
+        offset: u32,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<message::Message>> {
+        if let Ok(conn) = self.pool.get() {
+            let messages = match kind {
+                TranscriptKind::Contact(contact) => {
+                    let mut stmt = conn.prepare(
+                        "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                        WHERE owner = ?1 AND (sender = ?1 OR receiver = ?1) AND (receiver = ?2 OR sender = ?2) AND participants IS NULL \
+                        ORDER BY timestamp, id LIMIT ?3 OFFSET ?4",
+                    )?;
+
+                    stmt.query_map(
+                        params![user_email, contact, limit, offset],
+                        message_from_row,
+                    )?
+                    .collect()
+                }
+
+                TranscriptKind::Group(participants) => {
+                    let mut stmt = conn.prepare(
+                        "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                        WHERE owner = ?1 AND participants = ?2 ORDER BY timestamp, id LIMIT ?3 OFFSET ?4",
+                    )?;
+
+                    stmt.query_map(
+                        params![user_email, participants, limit, offset],
+                        message_from_row,
+                    )?
+                    .collect()
+                }
+            };
+
+            return messages;
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn select_messages_for_export(
+        &self,
+        user_email: &str,
+        contact: Option<&str>,
+    ) -> rusqlite::Result<Vec<message::Message>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants FROM messages \
+                WHERE owner = ?1 AND (?2 IS NULL OR sender = ?2 OR receiver = ?2) ORDER BY timestamp, id",
+            )?;
+
+            let messages = stmt.query_map(params![user_email, contact], message_from_row)?;
+            return messages.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn search_messages(
+        &self,
+        user_email: &str,
+        query: &str,
+        contact: Option<&str>,
+        from_timestamp: Option<i64>,
+        to_timestamp: Option<i64>,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<SearchResult>> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT sender, receiver, is_nudge, messages.text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants, \
+                snippet(messages_fts, 0, char(2), char(3), '...', 16) FROM messages_fts \
+                INNER JOIN messages ON messages.id = messages_fts.rowid \
+                WHERE messages_fts MATCH ?1 AND owner = ?2 AND is_nudge = FALSE \
+                AND (?3 IS NULL OR sender = ?3 OR receiver = ?3) \
+                AND (?4 IS NULL OR timestamp >= ?4) AND (?5 IS NULL OR timestamp < ?5) \
+                ORDER BY timestamp DESC, id DESC LIMIT ?6",
+            )?;
+
+            let results = stmt.query_map(
+                params![
+                    query,
+                    user_email,
+                    contact,
+                    from_timestamp,
+                    to_timestamp,
+                    limit
+                ],
+                |row| {
+                    Ok(SearchResult {
+                        message: message_from_row(row)?,
+                        snippet: row.get(14)?,
+                    })
+                },
+            );
+
+            return results?.collect();
         }
 
         Err(rusqlite::Error::QueryReturnedNoRows)
@@ -257,15 +464,24 @@ impl Sqlite {
     ) -> rusqlite::Result<()> {
         if let Ok(conn) = self.pool.get() {
             conn.execute(
-                "INSERT INTO display_pictures (picture, hash) VALUES (?1, ?2)",
-                params![display_picture, display_picture_hash],
+                "INSERT INTO display_pictures (picture, hash, last_seen) VALUES (?1, ?2, ?3) \
+                ON CONFLICT (hash) DO UPDATE SET last_seen = excluded.last_seen",
+                params![
+                    display_picture,
+                    display_picture_hash,
+                    chrono::Local::now().timestamp()
+                ],
             )?;
         }
 
         Ok(())
     }
 
-    pub fn insert_message(&self, message: &message::Message) -> rusqlite::Result<()> {
+    pub fn insert_message(
+        &self,
+        user_email: &str,
+        message: &message::Message,
+    ) -> rusqlite::Result<i64> {
         if let Ok(conn) = self.pool.get() {
             conn.execute(
                 "INSERT INTO messages (\
@@ -277,8 +493,13 @@ impl Sqlite {
                 italic,\
                 underline,\
                 strikethrough,\
-                session_id\
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                session_id,\
+                timestamp,\
+                errored,\
+                color,\
+                participants,\
+                owner\
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
                 params![
                     message.sender,
                     message.receiver,
@@ -288,7 +509,443 @@ impl Sqlite {
                     message.italic,
                     message.underline,
                     message.strikethrough,
-                    message.session_id
+                    message.session_id,
+                    message.timestamp,
+                    message.errored,
+                    message.color,
+                    message.participants,
+                    user_email
+                ],
+            )?;
+
+            return Ok(conn.last_insert_rowid());
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Returns how many messages were inserted, skipping the ones that are already stored
+    pub fn insert_imported_messages(
+        &self,
+        user_email: &str,
+        messages: &[message::Message],
+    ) -> rusqlite::Result<usize> {
+        if let Ok(mut conn) = self.pool.get() {
+            let tx = conn.transaction()?;
+            let mut inserted = 0;
+            {
+                let mut exists_stmt = tx.prepare(
+                    "SELECT id FROM messages WHERE owner = ?1 AND sender = ?2 AND receiver IS ?3 \
+                    AND session_id IS ?4 AND timestamp = ?5 AND text = ?6",
+                )?;
+
+                let mut insert_stmt = tx.prepare(
+                    "INSERT INTO messages (\
+                    sender,\
+                    receiver,\
+                    is_nudge,\
+                    text,\
+                    bold,\
+                    italic,\
+                    underline,\
+                    strikethrough,\
+                    session_id,\
+                    timestamp,\
+                    errored,\
+                    color,\
+                    participants,\
+                    owner\
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                )?;
+
+                for message in messages {
+                    if exists_stmt
+                        .query(params![
+                            user_email,
+                            message.sender,
+                            message.receiver,
+                            message.session_id,
+                            message.timestamp,
+                            message.text
+                        ])?
+                        .count()?
+                        > 0
+                    {
+                        continue;
+                    }
+
+                    insert_stmt.execute(params![
+                        message.sender,
+                        message.receiver,
+                        message.is_nudge,
+                        message.text,
+                        message.bold,
+                        message.italic,
+                        message.underline,
+                        message.strikethrough,
+                        message.session_id,
+                        message.timestamp,
+                        message.errored,
+                        message.color,
+                        message.participants,
+                        user_email
+                    ])?;
+
+                    inserted += 1;
+                }
+            }
+
+            tx.commit()?;
+            return Ok(inserted);
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Messages the migration couldn't attribute to an account go to the first one that signs in
+    // and took part in them
+    pub fn claim_unowned_messages(&self, user_email: &str) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "UPDATE messages SET owner = ?1 WHERE owner IS NULL AND (sender = ?1 OR receiver = ?1)",
+                [user_email],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn select_outbox(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+    ) -> rusqlite::Result<Vec<OutboxEntry>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT sender, receiver, is_nudge, text, bold, italic, underline, strikethrough, session_id, timestamp, id, errored, color, participants, \
+                attempts, next_attempt, sending FROM outbox \
+                INNER JOIN messages ON messages.id = outbox.message_id \
+                WHERE owner = ?1 AND receiver = ?2 ORDER BY timestamp, id",
+            )?;
+
+            let entries = stmt.query_map([user_email, contact_email], |row| {
+                Ok(OutboxEntry {
+                    message: message_from_row(row)?,
+                    attempts: row.get(14)?,
+                    next_attempt: row.get(15)?,
+                    sending: row.get(16)?,
+                })
+            });
+
+            return entries?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // The contacts with messages in the outbox that are still being retried
+    pub fn select_outbox_contacts(
+        &self,
+        user_email: &str,
+        max_attempts: u32,
+    ) -> rusqlite::Result<Vec<String>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT receiver FROM outbox \
+                INNER JOIN messages ON messages.id = outbox.message_id \
+                WHERE owner = ?1 AND receiver IS NOT NULL AND attempts < ?2",
+            )?;
+
+            let contacts = stmt.query_map(params![user_email, max_attempts], |row| row.get(0));
+            return contacts?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Adds the message to the outbox, or reschedules it if it's already there
+    pub fn schedule_outbox_message(
+        &self,
+        message_id: i64,
+        attempts: u32,
+        next_attempt: i64,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "INSERT INTO outbox (message_id, attempts, next_attempt) VALUES (?1, ?2, ?3) \
+                ON CONFLICT (message_id) DO UPDATE SET attempts = ?2, next_attempt = ?3, sending = FALSE",
+                params![message_id, attempts, next_attempt],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Counts an attempt and marks the message as being sent until its result comes back
+    pub fn start_outbox_attempt(
+        &self,
+        message_id: i64,
+        attempts: u32,
+        next_attempt: i64,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "UPDATE outbox SET attempts = ?2, next_attempt = ?3, sending = TRUE WHERE message_id = ?1",
+                params![message_id, attempts, next_attempt],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn fail_outbox_attempt(&self, message_id: i64) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "UPDATE outbox SET sending = FALSE WHERE message_id = ?1",
+                [message_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_message_recipients(
+        &self,
+        message_id: i64,
+        receiver: Option<&str>,
+        participants: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "UPDATE messages SET receiver = ?2, participants = ?3 WHERE id = ?1",
+                params![message_id, receiver, participants],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn mark_message_delivered(&self, message_id: i64) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "UPDATE messages SET errored = FALSE WHERE id = ?1",
+                [message_id],
+            )?;
+
+            conn.execute("DELETE FROM outbox WHERE message_id = ?1", [message_id])?;
+        }
+
+        Ok(())
+    }
+
+    // The contact list as last received from the server, to show before it's synced again
+    pub fn select_contacts(&self, user_email: &str) -> rusqlite::Result<Vec<Contact>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT email, display_name, guid, lists, personal_message, picture, hash, friendly_name \
+                FROM contacts \
+                LEFT JOIN display_pictures ON contacts.display_picture_hash = display_pictures.hash \
+                WHERE owner = ?1",
+            )?;
+
+            let contacts = stmt.query_map([user_email], |row| {
+                Ok(Contact {
+                    email: Arc::new(row.get(0)?),
+                    display_name: Arc::new(row.get(1)?),
+                    guid: row.get::<usize, Option<String>>(2)?.map(Arc::new),
+                    lists: lists_from_string(&row.get::<usize, String>(3)?),
+                    personal_message: row.get::<usize, Option<String>>(4)?.map(Arc::new),
+                    display_picture: if let Ok(picture) = row.get(5)
+                        && let Ok(hash) = row.get(6)
+                    {
+                        Some(DisplayPicture {
+                            data: picture,
+                            hash: Arc::new(hash),
+                        })
+                    } else {
+                        None
+                    },
+                    friendly_name: row.get::<usize, Option<String>>(7)?.map(Arc::new),
+                    ..Default::default()
+                })
+            });
+
+            return contacts?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Writes the cached contacts in one transaction, deleting the ones passed without a contact
+    pub fn write_contacts(
+        &self,
+        user_email: &str,
+        contacts: &[(Arc<String>, Option<Contact>)],
+    ) -> rusqlite::Result<()> {
+        if let Ok(mut conn) = self.pool.get() {
+            let tx = conn.transaction()?;
+            {
+                let mut upsert_stmt = tx.prepare(
+                    "INSERT INTO contacts (owner, email, display_name, guid, lists, personal_message, display_picture_hash, \
+                    friendly_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) ON CONFLICT (owner, email) DO UPDATE SET \
+                    display_name = ?3, guid = ?4, lists = ?5, personal_message = ?6, display_picture_hash = ?7, \
+                    friendly_name = ?8",
+                )?;
+
+                let mut delete_stmt =
+                    tx.prepare("DELETE FROM contacts WHERE owner = ?1 AND email = ?2")?;
+
+                for (email, contact) in contacts {
+                    if let Some(contact) = contact {
+                        upsert_stmt.execute(params![
+                            user_email,
+                            contact.email,
+                            contact.display_name,
+                            contact.guid,
+                            lists_to_string(&contact.lists),
+                            contact.personal_message,
+                            contact
+                                .display_picture
+                                .as_ref()
+                                .map(|picture| picture.hash.clone()),
+                            contact.friendly_name,
+                        ])?;
+                    } else {
+                        delete_stmt.execute(params![user_email, email])?;
+                    }
+                }
+            }
+
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn select_contact_aliases(
+        &self,
+        user_email: &str,
+    ) -> rusqlite::Result<Vec<(String, String)>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt =
+                conn.prepare("SELECT email, alias FROM contact_aliases WHERE owner = ?1")?;
+
+            let aliases = stmt.query_map([user_email], |row| Ok((row.get(0)?, row.get(1)?)));
+            return aliases?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Removes the contact's alias if None is passed
+    pub fn set_contact_alias(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+        alias: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            if let Some(alias) = alias {
+                conn.execute(
+                    "INSERT INTO contact_aliases (owner, email, alias) VALUES (?1, ?2, ?3) \
+                    ON CONFLICT (owner, email) DO UPDATE SET alias = excluded.alias",
+                    [user_email, contact_email, alias],
+                )?;
+            } else {
+                conn.execute(
+                    "DELETE FROM contact_aliases WHERE owner = ?1 AND email = ?2",
+                    [user_email, contact_email],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn select_contact_alerts(
+        &self,
+        user_email: &str,
+    ) -> rusqlite::Result<Vec<(String, AlertRule)>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT email, on_sign_in, on_sign_out, on_status_change, on_personal_message, notify, request_attention, highlight \
+                FROM contact_alerts WHERE owner = ?1",
+            )?;
+
+            let alerts = stmt.query_map([user_email], |row| {
+                Ok((
+                    row.get(0)?,
+                    AlertRule {
+                        on_sign_in: row.get(1)?,
+                        on_sign_out: row.get(2)?,
+                        on_status_change: row.get(3)?,
+                        on_personal_message: row.get(4)?,
+                        notify: row.get(5)?,
+                        request_attention: row.get(6)?,
+                        highlight: row.get(7)?,
+                    },
+                ))
+            });
+
+            return alerts?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Removes the contact's alert rule if None is passed
+    pub fn set_contact_alert(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+        rule: Option<&AlertRule>,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            if let Some(rule) = rule {
+                conn.execute(
+                    "INSERT INTO contact_alerts (owner, email, on_sign_in, on_sign_out, on_status_change, on_personal_message, notify, request_attention, highlight) \
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) \
+                    ON CONFLICT (owner, email) DO UPDATE SET on_sign_in = excluded.on_sign_in, on_sign_out = excluded.on_sign_out, \
+                    on_status_change = excluded.on_status_change, on_personal_message = excluded.on_personal_message, \
+                    notify = excluded.notify, request_attention = excluded.request_attention, highlight = excluded.highlight",
+                    params![
+                        user_email,
+                        contact_email,
+                        rule.on_sign_in,
+                        rule.on_sign_out,
+                        rule.on_status_change,
+                        rule.on_personal_message,
+                        rule.notify,
+                        rule.request_attention,
+                        rule.highlight
+                    ],
+                )?;
+            } else {
+                conn.execute(
+                    "DELETE FROM contact_alerts WHERE owner = ?1 AND email = ?2",
+                    [user_email, contact_email],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn insert_presence(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+        status: Option<&MsnpStatus>,
+        timestamp: i64,
+    ) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute(
+                "INSERT INTO presence_history (owner, email, status, timestamp) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    user_email,
+                    contact_email,
+                    status.map(status_to_string),
+                    timestamp
                 ],
             )?;
         }
@@ -296,6 +953,65 @@ impl Sqlite {
         Ok(())
     }
 
+    // Returns the last time each contact's presence changed while the user was signed in
+    pub fn select_last_seen(&self, user_email: &str) -> rusqlite::Result<Vec<(String, i64)>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT email, MAX(timestamp) FROM presence_history WHERE owner = ?1 GROUP BY email",
+            )?;
+
+            let last_seen = stmt.query_map([user_email], |row| Ok((row.get(0)?, row.get(1)?)));
+            return last_seen?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Selects the presence changes since the given timestamp, starting with the last one before it
+    // so the presence at that moment is known
+    pub fn select_presence_history(
+        &self,
+        user_email: &str,
+        contact_email: &str,
+        since: i64,
+    ) -> rusqlite::Result<Vec<PresenceEntry>> {
+        if let Ok(conn) = self.pool.get() {
+            let mut stmt = conn.prepare(
+                "SELECT status, timestamp FROM presence_history WHERE owner = ?1 AND email = ?2 \
+                AND timestamp >= (SELECT COALESCE(MAX(timestamp), 0) FROM presence_history WHERE owner = ?1 AND email = ?2 AND timestamp <= ?3) \
+                ORDER BY timestamp, id",
+            )?;
+
+            let entries = stmt.query_map(params![user_email, contact_email, since], |row| {
+                Ok(PresenceEntry {
+                    status: row
+                        .get::<usize, Option<String>>(0)?
+                        .and_then(|status| status_from_string(&status)),
+                    timestamp: row.get(1)?,
+                })
+            });
+
+            return entries?.collect();
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn delete_presence_history_before(
+        &self,
+        user_email: &str,
+        before: i64,
+    ) -> rusqlite::Result<usize> {
+        if let Ok(conn) = self.pool.get() {
+            return conn.execute(
+                "DELETE FROM presence_history WHERE owner = ?1 AND timestamp < ?2",
+                params![user_email, before],
+            );
+        }
+
+        Ok(0)
+    }
+
     pub fn update_personal_message(
         &self,
         email: &str,
@@ -332,11 +1048,314 @@ impl Sqlite {
         Err(rusqlite::Error::QueryReturnedNoRows)
     }
 
+    pub fn delete_transcript_messages(
+        &self,
+        user_email: &str,
+        kind: &TranscriptKind,
+    ) -> rusqlite::Result<usize> {
+        if let Ok(conn) = self.pool.get() {
+            let (filter, key) = transcript_filter(kind);
+            return conn.execute(
+                &format!("DELETE FROM messages WHERE {filter}"),
+                params![user_email, key],
+            );
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn delete_transcript_messages_before(
+        &self,
+        user_email: &str,
+        kind: &TranscriptKind,
+        timestamp: i64,
+    ) -> rusqlite::Result<usize> {
+        if let Ok(conn) = self.pool.get() {
+            // Messages without a known date are never old enough to prune by age
+            let (filter, key) = transcript_filter(kind);
+            return conn.execute(
+                &format!(
+                    "DELETE FROM messages WHERE {filter} AND timestamp != 0 AND timestamp < ?3"
+                ),
+                params![user_email, key, timestamp],
+            );
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn delete_transcript_messages_beyond(
+        &self,
+        user_email: &str,
+        kind: &TranscriptKind,
+        keep: u32,
+    ) -> rusqlite::Result<usize> {
+        if let Ok(conn) = self.pool.get() {
+            let (filter, key) = transcript_filter(kind);
+            return conn.execute(
+                &format!(
+                    "DELETE FROM messages WHERE {filter} AND id NOT IN \
+                    (SELECT id FROM messages WHERE {filter} ORDER BY timestamp DESC, id DESC LIMIT ?3)"
+                ),
+                params![user_email, key, keep],
+            );
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // Deletes pictures no account or cached contact uses that no contact has shown since the
+    // timestamp
+    pub fn delete_unused_display_pictures(&self, seen_before: i64) -> rusqlite::Result<usize> {
+        if let Ok(conn) = self.pool.get() {
+            return conn.execute(
+                "DELETE FROM display_pictures WHERE last_seen < ?1 AND id NOT IN \
+                (SELECT display_picture_id FROM users WHERE display_picture_id IS NOT NULL) \
+                AND hash NOT IN \
+                (SELECT display_picture_hash FROM contacts WHERE display_picture_hash IS NOT NULL)",
+                [seen_before],
+            );
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn database_size(&self) -> rusqlite::Result<u64> {
+        if let Ok(conn) = self.pool.get() {
+            let page_count: u32 = conn.pragma_query_value(None, "page_count", |row| row.get(0))?;
+            let page_size: u32 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+            return Ok(u64::from(page_count) * u64::from(page_size));
+        }
+
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn vacuum(&self) -> rusqlite::Result<()> {
+        if let Ok(conn) = self.pool.get() {
+            conn.execute("VACUUM", ())?;
+        }
+
+        Ok(())
+    }
+
     pub fn delete_user(&self, email: &str) -> rusqlite::Result<()> {
         if let Ok(conn) = self.pool.get() {
+            let display_picture_id: Option<i64> = conn
+                .query_row(
+                    "SELECT display_picture_id FROM users WHERE email = ?1",
+                    [email],
+                    |row| row.get(0),
+                )
+                .unwrap_or_default();
+
             conn.execute("DELETE FROM users WHERE email = ?1", [email])?;
+            conn.execute("DELETE FROM contacts WHERE owner = ?1", [email])?;
+            conn.execute("DELETE FROM contact_aliases WHERE owner = ?1", [email])?;
+            conn.execute("DELETE FROM contact_alerts WHERE owner = ?1", [email])?;
+            conn.execute("DELETE FROM presence_history WHERE owner = ?1", [email])?;
+            if let Some(display_picture_id) = display_picture_id {
+                conn.execute(
+                    "DELETE FROM display_pictures WHERE id = ?1 AND id NOT IN \
+                    (SELECT display_picture_id FROM users WHERE display_picture_id IS NOT NULL)",
+                    [display_picture_id],
+                )?;
+            }
         }
 
         Ok(())
     }
 }
+
+fn database_path() -> anyhow::Result<PathBuf> {
+    // Compatibility with previous name
+    let mut old_data_local =
+        dirs::data_local_dir().context("Could not find local data directory")?;
+
+    old_data_local.push("icedm");
+
+    let mut data_local = dirs::data_local_dir().context("Could not find local data directory")?;
+    data_local.push("meowsn");
+
+    if old_data_local.exists() {
+        std::fs::rename(old_data_local.clone(), data_local.clone())?;
+    }
+
+    std::fs::create_dir_all(&data_local)?;
+
+    let mut old_data_local = data_local.clone();
+    old_data_local.push("icedm");
+    old_data_local.set_extension("db");
+
+    data_local.push("meowsn");
+    data_local.set_extension("db");
+
+    if old_data_local.exists() {
+        std::fs::rename(old_data_local, data_local.clone())?;
+    }
+
+    Ok(data_local)
+}
+
+pub fn database_is_encrypted() -> anyhow::Result<bool> {
+    let data_local = database_path()?;
+    if !data_local.exists() {
+        return Ok(false);
+    }
+
+    // Plaintext databases always start with this header, encrypted ones look like random bytes
+    let mut header = [0; 16];
+    let read = std::fs::File::open(&data_local)?.read(&mut header)?;
+    Ok(read > 0 && header != *b"SQLite format 3\0")
+}
+
+// SQLCipher reports a wrong key as the file not being a database
+pub fn is_wrong_key(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<rusqlite::Error>()
+            .and_then(|error| error.sqlite_error_code())
+            == Some(rusqlite::ErrorCode::NotADatabase)
+    })
+}
+
+// Rewrites the database encrypted with the key, or decrypts it back to plaintext
+fn convert_database(path: &Path, key: Option<&str>, encrypt: bool) -> anyhow::Result<()> {
+    let key = key.context("A passphrase is needed to convert the database")?;
+    let converted_path = path.with_extension("db.converting");
+    if converted_path.exists() {
+        std::fs::remove_file(&converted_path)?;
+    }
+
+    let conn = rusqlite::Connection::open(path)?;
+    if !encrypt {
+        conn.pragma_update(None, "key", key)?;
+    }
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS converted KEY ?2",
+        params![
+            converted_path.to_string_lossy(),
+            if encrypt { key } else { "" }
+        ],
+    )
+    .context("Could not open the database, the passphrase may be wrong")?;
+
+    conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+    let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    conn.pragma_update(Some("converted"), "user_version", version)?;
+    conn.execute("DETACH DATABASE converted", ())?;
+
+    // The original is kept as it was, so a failed conversion can't lose the history
+    let backup_path = path.with_extension("db.unconverted.bak");
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path)?;
+    }
+
+    conn.execute(
+        "VACUUM INTO ?1",
+        [backup_path.to_string_lossy().to_string()],
+    )
+    .context("Could not back up the database before converting it")?;
+
+    drop(conn);
+
+    std::fs::rename(&converted_path, path)?;
+    Ok(())
+}
+
+// Returns the condition matching a transcript's messages, taking the account as ?1 and the
+// returned key as ?2
+fn transcript_filter(kind: &TranscriptKind) -> (&'static str, &Arc<String>) {
+    match kind {
+        TranscriptKind::Contact(contact) => (
+            "owner = ?1 AND participants IS NULL AND (sender = ?2 OR receiver = ?2)",
+            contact,
+        ),
+
+        TranscriptKind::Group(participants) => ("owner = ?1 AND participants = ?2", participants),
+    }
+}
+
+fn status_to_string(status: &MsnpStatus) -> &'static str {
+    match status {
+        MsnpStatus::Online => "NLN",
+        MsnpStatus::Busy => "BSY",
+        MsnpStatus::Away => "AWY",
+        MsnpStatus::Idle => "IDL",
+        MsnpStatus::OutToLunch => "LUN",
+        MsnpStatus::OnThePhone => "PHN",
+        MsnpStatus::BeRightBack => "BRB",
+        MsnpStatus::AppearOffline => "HDN",
+    }
+}
+
+fn status_from_string(status: &str) -> Option<MsnpStatus> {
+    match status {
+        "NLN" => Some(MsnpStatus::Online),
+        "BSY" => Some(MsnpStatus::Busy),
+        "AWY" => Some(MsnpStatus::Away),
+        "IDL" => Some(MsnpStatus::Idle),
+        "LUN" => Some(MsnpStatus::OutToLunch),
+        "PHN" => Some(MsnpStatus::OnThePhone),
+        "BRB" => Some(MsnpStatus::BeRightBack),
+        "HDN" => Some(MsnpStatus::AppearOffline),
+        _ => None,
+    }
+}
+
+fn lists_to_string(lists: &[MsnpList]) -> String {
+    lists
+        .iter()
+        .map(|list| match list {
+            MsnpList::ForwardList => "FL",
+            MsnpList::AllowList => "AL",
+            MsnpList::BlockList => "BL",
+            MsnpList::ReverseList => "RL",
+            MsnpList::PendingList => "PL",
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn lists_from_string(lists: &str) -> Vec<MsnpList> {
+    lists
+        .split_whitespace()
+        .filter_map(|list| match list {
+            "FL" => Some(MsnpList::ForwardList),
+            "AL" => Some(MsnpList::AllowList),
+            "BL" => Some(MsnpList::BlockList),
+            "RL" => Some(MsnpList::ReverseList),
+            "PL" => Some(MsnpList::PendingList),
+            _ => None,
+        })
+        .collect()
+}
+
+fn message_from_row(row: &rusqlite::Row) -> rusqlite::Result<message::Message> {
+    Ok(message::Message {
+        id: row.get(10)?,
+        sender: Arc::new(row.get(0)?),
+        receiver: row.get(1).ok().map(Arc::new),
+        is_nudge: row.get(2)?,
+        text: row.get(3)?,
+        bold: row.get(4)?,
+        italic: row.get(5)?,
+        underline: row.get(6)?,
+        strikethrough: row.get(7)?,
+        session_id: row.get(8).ok().map(Arc::new),
+        participants: row.get(13).ok().map(Arc::new),
+        color: row.get(12)?,
+        is_history: true,
+        errored: row.get(11)?,
+        timestamp: row.get(9)?,
+    })
+}
+
+// Quoting every word keeps user input from being parsed as FTS5 syntax
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -147,6 +147,25 @@ pub fn dark_mode(old: egui::Visuals) -> egui::Visuals {
     }
 }
 
+// Lightens dark message colors in dark mode so they stay readable against the background
+pub fn readable_message_color(color: Color32, dark_mode: bool) -> Color32 {
+    const MIN_LUMINANCE: f32 = 0.5;
+
+    let luminance = (0.2126 * f32::from(color.r())
+        + 0.7152 * f32::from(color.g())
+        + 0.0722 * f32::from(color.b()))
+        / 255.;
+
+    if !dark_mode || luminance >= MIN_LUMINANCE {
+        return color;
+    }
+
+    color.lerp_to_gamma(
+        Color32::WHITE,
+        (MIN_LUMINANCE - luminance) / (1. - luminance),
+    )
+}
+
 pub fn load_fonts() -> FontDefinitions {
     let mut font_definitions = FontDefinitions::default();
 